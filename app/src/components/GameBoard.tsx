@@ -2,7 +2,7 @@ import { useEffect, useState, useRef, useCallback } from "react";
 import { useWallet, useAnchorWallet, useConnection } from "@solana/wallet-adapter-react";
 import { AnchorProvider } from "@coral-xyz/anchor";
 import { Connection, PublicKey } from "@solana/web3.js";
-import { getProgram, getPlayerPda, getBoardPda, getConnection, ER_ENDPOINT, ER_WS, BOARD_SIZE, ER_VALIDATORS, getDelegationPda, getCommitStatePda, DELEGATION_PROGRAM_ID } from "@/lib/anchor";
+import { getProgram, getPlayerPda, getBoardPda, getOccupancyPda, playersOnBoard, getConnection, ER_ENDPOINT, ER_WS, BOARD_SIZE, ER_VALIDATORS, getDelegationPda, getCommitStatePda, DELEGATION_PROGRAM_ID, BOARD_ID, BOARD_CONFIG, COMMIT_FREQUENCY_MS, DELEGATION_TIME_LIMIT } from "@/lib/anchor";
 import { Button } from "@/components/ui/button";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
 import { toast } from "sonner";
@@ -95,7 +95,7 @@ export function GameBoard() {
           x: playerAccount.x,
           y: playerAccount.y,
           authority: playerAccount.authority.toString(),
-          sessionKey: playerAccount.sessionKey?.toString() || null,
+          sessionKey: playerAccount.sessions[0]?.key.toString() || null,
           isDelegated: false,
         });
       } catch (e) {
@@ -119,7 +119,7 @@ export function GameBoard() {
             x: erPlayerAccount.x,
             y: erPlayerAccount.y,
             authority: erPlayerAccount.authority.toString(),
-            sessionKey: erPlayerAccount.sessionKey?.toString() || null,
+            sessionKey: erPlayerAccount.sessions[0]?.key.toString() || null,
             isDelegated: true,
           });
         } catch (e) {
@@ -152,7 +152,7 @@ export function GameBoard() {
             x: decodedData.x,
             y: decodedData.y,
             authority: decodedData.authority.toString(),
-            sessionKey: decodedData.sessionKey?.toString() || null,
+            sessionKey: decodedData.sessions[0]?.key.toString() || null,
             isDelegated: false,
           });
 
@@ -175,7 +175,7 @@ export function GameBoard() {
             x: decodedData.x,
             y: decodedData.y,
             authority: decodedData.authority.toString(),
-            sessionKey: decodedData.sessionKey?.toString() || null,
+            sessionKey: decodedData.sessions[0]?.key.toString() || null,
             isDelegated: true,
           });
         } catch (error) {
@@ -282,7 +282,7 @@ export function GameBoard() {
 
         let erPlayers = [];
         try {
-          erPlayers = await (erProgram.account as any).player.all();
+          erPlayers = await (erProgram.account as any).player.all(playersOnBoard());
         } catch (e) {
           console.log("Could not fetch ER players:", e);
         }
@@ -294,7 +294,7 @@ export function GameBoard() {
             x: erPlayerAccount.account.x,
             y: erPlayerAccount.account.y,
             authority,
-            sessionKey: erPlayerAccount.account.sessionKey?.toString() || null,
+            sessionKey: erPlayerAccount.account.sessions[0]?.key.toString() || null,
             isDelegated: true,
           });
         }
@@ -303,7 +303,7 @@ export function GameBoard() {
         const baseProvider = new AnchorProvider(baseConnection.current, {} as any, {});
         const baseProgram = getProgram(baseProvider);
 
-        const basePlayers = await (baseProgram.account as any).player.all();
+        const basePlayers = await (baseProgram.account as any).player.all(playersOnBoard());
 
         // Process base layer players
         for (const playerAccount of basePlayers) {
@@ -318,7 +318,7 @@ export function GameBoard() {
             x: playerAccount.account.x,
             y: playerAccount.account.y,
             authority,
-            sessionKey: playerAccount.account.sessionKey?.toString() || null,
+            sessionKey: playerAccount.account.sessions[0]?.key.toString() || null,
             isDelegated: delegated,
           });
         }
@@ -342,7 +342,16 @@ export function GameBoard() {
       });
       const program = getProgram(provider);
 
-      const tx = await program.methods.initialize().rpc({ skipPreflight: false });
+      // Allow the ER validator players delegate to in the same transaction
+      const addValidator = await program.methods
+        .addValidator(ER_VALIDATORS.asia)
+        .accounts({ board: getBoardPda() })
+        .instruction();
+
+      const tx = await program.methods
+        .initialize(BOARD_ID, BOARD_CONFIG)
+        .postInstructions([addValidator])
+        .rpc({ skipPreflight: false });
 
       // Wait a bit for confirmation on devnet
       await new Promise(resolve => setTimeout(resolve, 2000));
@@ -383,7 +392,10 @@ export function GameBoard() {
       });
       const program = getProgram(provider);
 
-      const tx = await program.methods.joinGame().rpc({ skipPreflight: false });
+      const tx = await program.methods
+        .joinGame()
+        .accounts({ board: getBoardPda() })
+        .rpc({ skipPreflight: false });
 
       // Wait for confirmation
       await new Promise(resolve => setTimeout(resolve, 2000));
@@ -444,15 +456,20 @@ export function GameBoard() {
       const program = getProgram(provider);
       const playerPda = getPlayerPda(publicKey);
 
+      // Moves carry the player's current nonce so a replayed move is rejected
+      const { moveNonce } = await (program.account as any).player.fetch(playerPda);
+
       console.log("Sending move transaction:", {
         playerPda: playerPda.toString(),
         signer: activeWallet.publicKey.toString(),
+        moveNonce: moveNonce.toString(),
       });
 
       const tx = await program.methods
-        .movePlayer(xDir, yDir)
+        .movePlayer(xDir, yDir, moveNonce)
         .accounts({
           player: playerPda,
+          board: getBoardPda(),
           signer: activeWallet.publicKey,
         })
         .rpc();
@@ -483,17 +500,39 @@ export function GameBoard() {
       const provider = new AnchorProvider(baseConnection.current, wallet, {});
       const program = getProgram(provider);
       const playerPda = getPlayerPda(publicKey);
+      const board = getBoardPda();
+      const validator = { pubkey: ER_VALIDATORS.asia, isSigner: false, isWritable: false };
+
+      // Moves write the board's occupancy, so the match starts once it is in the ER
+      const preInstructions = [];
+      const occupancy = await baseConnection.current.getAccountInfo(getOccupancyPda(board));
+      if (!occupancy?.owner.equals(DELEGATION_PROGRAM_ID)) {
+        const boardAccount = await (program.account as any).board.fetch(board);
+        if (!boardAccount.authority.equals(wallet.publicKey)) {
+          toast.error("The match has not started yet", {
+            description: "The board authority has to open it in the Ephemeral Rollup first",
+          });
+          return;
+        }
+        preInstructions.push(
+          await program.methods
+            .delegateOccupancy()
+            .accounts({ payer: wallet.publicKey, authority: wallet.publicKey, board })
+            .remainingAccounts([validator])
+            .instruction()
+        );
+      }
 
       const tx = await program.methods
-        .delegatePlayer()
+        .delegatePlayer(COMMIT_FREQUENCY_MS, DELEGATION_TIME_LIMIT)
         .accounts({
           payer: wallet.publicKey,
           authority: wallet.publicKey,
+          board,
           pda: playerPda,
         })
-        .remainingAccounts([
-          { pubkey: ER_VALIDATORS.asia, isSigner: false, isWritable: false }
-        ])
+        .remainingAccounts([validator])
+        .preInstructions(preInstructions)
         .rpc();
 
       toast.success("Player delegated to Ephemeral Rollup!", {
@@ -531,6 +570,7 @@ export function GameBoard() {
         .undelegatePlayer()
         .accounts({
           payer: wallet.publicKey,
+          board: getBoardPda(),
           player: playerPda,
           magicProgram: new PublicKey("Magic11111111111111111111111111111111111111"),
           magicContext: new PublicKey("MagicContext1111111111111111111111111111111"),
@@ -575,6 +615,7 @@ export function GameBoard() {
         .commitPlayer()
         .accounts({
           payer: wallet.publicKey,
+          board: getBoardPda(),
           player: playerPda,
           magicProgram: new PublicKey("Magic11111111111111111111111111111111111111"),
           magicContext: new PublicKey("MagicContext1111111111111111111111111111111"),
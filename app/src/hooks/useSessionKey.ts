@@ -2,8 +2,8 @@ import { useEffect, useState } from "react";
 import { useWallet, useAnchorWallet } from "@solana/wallet-adapter-react";
 import { Keypair, PublicKey } from "@solana/web3.js";
 import { AnchorProvider } from "@coral-xyz/anchor";
-import { getProgram, getPlayerPda, getConnection, getConnectionForAccount } from "@/lib/anchor";
-import { getOrCreateSessionKey, clearSessionKey, SessionWallet, fundSessionKey, hasSessionKey } from "@/lib/sessionKey";
+import { getProgram, getPlayerPda, getBoardPda, getConnection, getConnectionForAccount, SESSION_LABEL, SESSION_DURATION, SESSION_SCOPE_MOVE_PLAYER, SESSION_MAX_USES, SESSION_TOP_UP } from "@/lib/anchor";
+import { getOrCreateSessionKey, clearSessionKey, SessionWallet, hasSessionKey } from "@/lib/sessionKey";
 import { toast } from "sonner";
 
 export function useSessionKey() {
@@ -91,7 +91,10 @@ export function useSessionKey() {
       const correctProgram = getProgram(correctProvider);
 
       const player = await (correctProgram.account as any).player.fetch(playerPda);
-      const hasRegisteredKey = player.sessionKey !== null;
+      const now = Date.now() / 1000;
+      const hasRegisteredKey = player.sessions.some(
+        (session: any) => session.expiresAt.toNumber() > now && session.usesRemaining > 0
+      );
       setIsRegistered(hasRegisteredKey);
 
       // Note: We don't automatically derive the session key here anymore
@@ -159,9 +162,16 @@ export function useSessionKey() {
       const provider = new AnchorProvider(connection, wallet, {});
       const program = getProgram(provider);
 
-      // Register session key on-chain
+      // Register session key on-chain; the key co-signs to prove it is held.
+      // Fees are funded separately through fundSessionKeyWallet.
       const tx = await program.methods
-        .registerSessionKey(key.publicKey)
+        .registerSessionKey(SESSION_LABEL, SESSION_DURATION, SESSION_SCOPE_MOVE_PLAYER, SESSION_MAX_USES)
+        .accounts({
+          player: getPlayerPda(publicKey),
+          board: getBoardPda(),
+          sessionKey: key.publicKey,
+        })
+        .signers([key])
         .rpc();
 
       const location = currentlyDelegated ? " on ER" : " on base layer";
@@ -198,8 +208,11 @@ export function useSessionKey() {
       }
 
       // Always use base layer connection for funding since that's where wallet SOL is
-      const baseConnection = getConnection();
-      const fundTx = await fundSessionKey(wallet, key.publicKey, baseConnection);
+      const baseProvider = new AnchorProvider(getConnection(), wallet, {});
+      const fundTx = await getProgram(baseProvider).methods
+        .fundSessionKey(SESSION_TOP_UP)
+        .accounts({ sessionKey: key.publicKey })
+        .rpc();
       toast.success("Session key funded!", {
         description: `Transferred 0.01 SOL for transaction fees`,
       });
@@ -232,7 +245,17 @@ export function useSessionKey() {
       const provider = new AnchorProvider(connection, wallet, {});
       const program = getProgram(provider);
 
-      const tx = await program.methods.revokeSessionKey().rpc();
+      // Revoke the key this browser holds, or every session if it is unknown
+      const playerPda = getPlayerPda(publicKey);
+      const tx = sessionKey
+        ? await program.methods
+            .revokeSessionKey(sessionKey.publicKey)
+            .accounts({ player: playerPda, signer: publicKey })
+            .rpc()
+        : await program.methods
+            .revokeAllSessions()
+            .accounts({ player: playerPda })
+            .rpc();
 
       const location = currentlyDelegated ? " on ER" : " on base layer";
       toast.success("Session key revoked" + location, {
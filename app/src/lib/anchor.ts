@@ -1,10 +1,40 @@
-import { AnchorProvider, Program } from "@coral-xyz/anchor";
+import { AnchorProvider, BN, Program } from "@coral-xyz/anchor";
 import { Connection, PublicKey } from "@solana/web3.js";
 import idl from "../idl/test_2.json";
 
 export const PROGRAM_ID = new PublicKey(idl.address);
 export const BOARD_SIZE = 100;
 
+// Board the app plays on; every board id is a separate game
+export const BOARD_ID = new BN(0);
+
+// Rules used when the app initializes the board
+export const BOARD_CONFIG = {
+  width: BOARD_SIZE,
+  height: BOARD_SIZE,
+  spawnX: 10,
+  spawnY: 10,
+  maxStep: 10,
+  maxPlayers: 64,
+  movementMode: { free: {} },
+  minCommitFrequencyMs: 1_000,
+  maxCommitFrequencyMs: 60_000,
+  maxDelegationTime: new BN(24 * 60 * 60),
+  moveCooldownTicks: 0,
+};
+
+// Delegation settings requested when a player enters the ER
+export const COMMIT_FREQUENCY_MS = 30_000;
+export const DELEGATION_TIME_LIMIT = new BN(60 * 60);
+
+// Session keys registered by the app may only move, for up to a day
+export const SESSION_LABEL = "browser";
+export const SESSION_DURATION = new BN(24 * 60 * 60);
+export const SESSION_SCOPE_MOVE_PLAYER = 1;
+export const SESSION_MAX_USES = 100_000;
+// Lamports sent to a session key for its fees, up to the program's cap of 0.01 SOL
+export const SESSION_TOP_UP = new BN(10_000_000);
+
 // Ephemeral Rollup endpoint (for delegated accounts)
 export const ER_ENDPOINT = "https://devnet.magicblock.app";
 export const ER_WS = "wss://devnet.magicblock.app";
@@ -48,22 +78,37 @@ export function getProgram(provider: AnchorProvider) {
   return new Program(idl as any, provider);
 }
 
-export function getBoardPda() {
+export function getBoardPda(boardId: BN = BOARD_ID) {
   const [pda] = PublicKey.findProgramAddressSync(
-    [Buffer.from("board")],
+    [Buffer.from("board"), boardId.toArrayLike(Buffer, "le", 8)],
     PROGRAM_ID
   );
   return pda;
 }
 
-export function getPlayerPda(authority: PublicKey) {
+export function getPlayerPda(authority: PublicKey, board: PublicKey = getBoardPda()) {
   const [pda] = PublicKey.findProgramAddressSync(
-    [Buffer.from("player"), authority.toBuffer()],
+    [Buffer.from("player"), board.toBuffer(), authority.toBuffer()],
     PROGRAM_ID
   );
   return pda;
 }
 
+// Occupancy bitmap of the board, which has to be in the ER before players
+export function getOccupancyPda(board: PublicKey = getBoardPda()) {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("occupancy"), board.toBuffer()],
+    PROGRAM_ID
+  );
+  return pda;
+}
+
+// Filter for `program.account.player.all()` matching players on the board.
+// Player data starts with the 8-byte discriminator and the 32-byte authority.
+export function playersOnBoard(board: PublicKey = getBoardPda()) {
+  return [{ memcmp: { offset: 8 + 32, bytes: board.toBase58() } }];
+}
+
 export function getConnection(useMagicRouter = false) {
   if (useMagicRouter) {
     // Use Magic Router for automatic routing between base layer and ER
@@ -1,4 +1,4 @@
-import { Keypair, PublicKey, Transaction, SystemProgram, Connection, VersionedTransaction } from "@solana/web3.js";
+import { Keypair, PublicKey, Transaction, Connection, VersionedTransaction } from "@solana/web3.js";
 import nacl from "tweetnacl";
 import type { AnchorWallet } from "@solana/wallet-adapter-react";
 import { sha256 } from "@noble/hashes/sha2.js";
@@ -123,51 +123,10 @@ export function hasSessionKey(walletPubkey: PublicKey): boolean {
   return localStorage.getItem(cacheKey) !== null;
 }
 
-/**
- * Fund session key with lamports so it can pay transaction fees
- * @param wallet - Main wallet (will sign and pay for this transfer)
- * @param sessionKeyPubkey - Session key public key to fund
- * @param connection - Solana connection
- * @param lamports - Amount of lamports to transfer (default: 0.01 SOL = 10_000_000 lamports)
- */
-export async function fundSessionKey(
-  wallet: AnchorWallet,
-  sessionKeyPubkey: PublicKey,
-  connection: Connection,
-  lamports: number = 10_000_000 // 0.01 SOL
-): Promise<string> {
-  const transaction = new Transaction().add(
-    SystemProgram.transfer({
-      fromPubkey: wallet.publicKey,
-      toPubkey: sessionKeyPubkey,
-      lamports,
-    })
-  );
-
-  const { blockhash, lastValidBlockHeight } = await connection.getLatestBlockhash();
-  transaction.recentBlockhash = blockhash;
-  transaction.feePayer = wallet.publicKey;
-
-  const signed = await wallet.signTransaction(transaction);
-  const signature = await connection.sendRawTransaction(signed.serialize(), {
-    skipPreflight: false,
-    preflightCommitment: "confirmed",
-  });
-
-  await connection.confirmTransaction({
-    signature,
-    blockhash,
-    lastValidBlockHeight,
-  }, "confirmed");
-
-  console.log(`Funded session key ${sessionKeyPubkey.toString()} with ${lamports} lamports`);
-  return signature;
-}
-
 /**
  * Session wallet that can sign transactions with session key
  * NOTE: Session key must have lamports to pay transaction fees
- * Use fundSessionKey() to transfer lamports to the session key after registration
+ * Use the program's fund_session_key instruction to send it lamports after registration
  */
 export class SessionWallet implements AnchorWallet {
   constructor(
@@ -1,80 +1,529 @@
 use anchor_lang::prelude::*;
-use ephemeral_rollups_sdk::anchor::{commit, delegate, ephemeral};
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::ZeroCopy;
+use std::cell::RefMut;
+use ephemeral_rollups_sdk::anchor::{commit, delegate, ephemeral, DelegationProgram};
 use ephemeral_rollups_sdk::cpi::DelegateConfig;
 use ephemeral_rollups_sdk::ephem::{commit_accounts, commit_and_undelegate_accounts};
 
 declare_id!("AqN6S5LJ4m1C5bQnr8996YFRu3jA1YnwaiG7eGEvD3oD");
 
-const BOARD_SIZE: u8 = 100;
-const INITIAL_X: u8 = 10;
-const INITIAL_Y: u8 = 10;
+/// Largest width or height a board can be configured with.
+pub const MAX_BOARD_SIZE: u8 = 100;
+/// Bytes of the occupancy bitmap, one bit per cell of the largest board.
+const OCCUPANCY_BYTES: usize = (MAX_BOARD_SIZE as usize * MAX_BOARD_SIZE as usize).div_ceil(8);
+/// Rows of the largest board held by each `BoardState` chunk.
+pub const BOARD_STATE_ROWS: u8 = 25;
+/// Number of `BoardState` chunks covering the largest board.
+pub const BOARD_STATE_CHUNKS: u16 = MAX_BOARD_SIZE.div_ceil(BOARD_STATE_ROWS) as u16;
+const BOARD_STATE_CELLS: usize = BOARD_STATE_ROWS as usize * MAX_BOARD_SIZE as usize;
+
+/// Most Ephemeral Rollup validators a board can approve for delegation.
+pub const MAX_VALIDATORS: usize = 8;
+
+/// Longest lifetime a session key can be registered for, in seconds.
+const MAX_SESSION_DURATION: i64 = 24 * 60 * 60;
+/// Most session keys a player can have registered at once.
+pub const MAX_SESSIONS: usize = 4;
+/// Longest label a session key can carry, in bytes.
+pub const MAX_SESSION_LABEL_LEN: usize = 16;
+/// Most lamports an authority can send a session key to cover its fees.
+pub const MAX_SESSION_TOP_UP: u64 = 10_000_000;
+/// Most steps a single `move_path` can take.
+pub const MAX_PATH_STEPS: usize = 32;
+/// Slots a player moving with a velocity of one takes to cross a cell.
+pub const SLOTS_PER_VELOCITY_STEP: u64 = 10;
+
+/// Bits of `SessionToken::scope`, one per instruction a session key may sign.
+pub const SCOPE_MOVE_PLAYER: u16 = 1 << 0;
+pub const SCOPE_COMMIT_PLAYER: u16 = 1 << 1;
+pub const SCOPE_UNDELEGATE_PLAYER: u16 = 1 << 2;
+pub const SCOPE_ALL: u16 = SCOPE_MOVE_PLAYER | SCOPE_COMMIT_PLAYER | SCOPE_UNDELEGATE_PLAYER;
 
 #[ephemeral]
 #[program]
 pub mod test_2 {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    pub fn initialize(ctx: Context<Initialize>, board_id: u64, config: BoardConfig) -> Result<()> {
+        config.validate()?;
+
         let board = &mut ctx.accounts.board;
+        board.id = board_id;
         board.authority = ctx.accounts.authority.key();
-        msg!("Board initialized by: {:?}", board.authority);
+        board.config = config;
+        board.player_count = 0;
+        board.paused = false;
+        board.allowed_validators = Vec::new();
+        board.tick = 0;
+        board.board_state_chunks = 0;
+        board.paused_at_slot = 0;
+        board.paused_slots = 0;
+        board.next_player_index = 1;
+        board.bump = ctx.bumps.board;
+        ctx.accounts.occupancy.load_init()?.board = board.key();
+
+        emit!(BoardInitialized {
+            board: board.key(),
+            board_id,
+            authority: board.authority,
+            config: board.config,
+        });
+        msg!("Board {} initialized by: {:?}", board.id, board.authority);
+        Ok(())
+    }
+
+    pub fn update_board_config(ctx: Context<UpdateBoard>, config: BoardConfig) -> Result<()> {
+        config.validate()?;
+
+        let board = &mut ctx.accounts.board;
+        // Players already placed must stay on the board
+        if config.width != board.config.width || config.height != board.config.height {
+            require!(board.player_count == 0, GameError::BoardNotEmpty);
+        }
+        require!(
+            config.max_players >= board.player_count,
+            GameError::InvalidBoardConfig
+        );
+        board.config = config;
+
+        emit!(BoardConfigUpdated {
+            board: board.key(),
+            config,
+        });
+        msg!("Board {} config updated", board.id);
+        Ok(())
+    }
+
+    pub fn set_paused(ctx: Context<UpdateBoard>, paused: bool) -> Result<()> {
+        let board = &mut ctx.accounts.board;
+        // Keep track of time spent paused so velocity doesn't drift through it
+        let slot = Clock::get()?.slot;
+        if paused && !board.paused {
+            board.paused_at_slot = slot;
+        } else if !paused && board.paused {
+            board.paused_slots += slot.saturating_sub(board.paused_at_slot);
+        }
+        board.paused = paused;
+
+        emit!(BoardPauseChanged {
+            board: board.key(),
+            paused,
+            slot,
+        });
+        msg!("Board {} paused: {}", board.id, paused);
+        Ok(())
+    }
+
+    pub fn transfer_board_authority(
+        ctx: Context<UpdateBoard>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let board = &mut ctx.accounts.board;
+        let previous_authority = board.authority;
+        board.authority = new_authority;
+
+        emit!(BoardAuthorityTransferred {
+            board: board.key(),
+            previous_authority,
+            new_authority,
+        });
+        msg!(
+            "Board {} authority transferred to {}",
+            board.id,
+            new_authority
+        );
+        Ok(())
+    }
+
+    pub fn add_validator(ctx: Context<UpdateBoard>, validator: Pubkey) -> Result<()> {
+        let board = &mut ctx.accounts.board;
+        if !board.allowed_validators.contains(&validator) {
+            require!(
+                board.allowed_validators.len() < MAX_VALIDATORS,
+                GameError::ValidatorListFull
+            );
+            board.allowed_validators.push(validator);
+        }
+
+        emit!(BoardValidatorChanged {
+            board: board.key(),
+            validator,
+            allowed: true,
+        });
+        msg!("Validator {} allowed on board {}", validator, board.id);
+        Ok(())
+    }
+
+    pub fn remove_validator(ctx: Context<UpdateBoard>, validator: Pubkey) -> Result<()> {
+        let board = &mut ctx.accounts.board;
+        board
+            .allowed_validators
+            .retain(|allowed| *allowed != validator);
+
+        emit!(BoardValidatorChanged {
+            board: board.key(),
+            validator,
+            allowed: false,
+        });
+        msg!("Validator {} removed from board {}", validator, board.id);
+        Ok(())
+    }
+
+    pub fn kick_player(ctx: Context<KickPlayer>) -> Result<()> {
+        let board = &mut ctx.accounts.board;
+        board.player_count -= 1;
+
+        let player = &ctx.accounts.player;
+        ctx.accounts
+            .occupancy
+            .load_mut()?
+            .set_occupied(&board.config, player.x, player.y, false);
+        BoardCells::load(board, &board.key(), ctx.remaining_accounts)?
+            .0
+            .set_occupant(player.x, player.y, 0);
+
+        emit!(PlayerLeft {
+            board: board.key(),
+            player: player.key(),
+            authority: player.authority,
+            kicked: true,
+        });
+        msg!(
+            "Player {} kicked from board {}",
+            ctx.accounts.player.authority,
+            board.id
+        );
+        Ok(())
+    }
+
+    pub fn close_board(ctx: Context<CloseBoard>) -> Result<()> {
+        require!(
+            ctx.accounts.board.player_count == 0,
+            GameError::BoardNotEmpty
+        );
+        // Board state chunks can only be closed through their board
+        require!(
+            ctx.accounts.board.board_state_chunks == 0,
+            GameError::BoardStateOpen
+        );
+
+        emit!(BoardClosed {
+            board: ctx.accounts.board.key(),
+            board_id: ctx.accounts.board.id,
+        });
+        msg!("Board {} closed", ctx.accounts.board.id);
         Ok(())
     }
 
+    /// Creates the player and places it on the board. This writes the board and
+    /// its occupancy on the base layer, so players have to join before the board
+    /// is delegated; while it is in the ER the lobby is closed.
     pub fn join_game(ctx: Context<JoinGame>) -> Result<()> {
+        let mut board = Board::load(&ctx.accounts.board)?;
+        require!(!board.paused, GameError::GamePaused);
+        require!(
+            board.player_count < board.config.max_players,
+            GameError::BoardFull
+        );
+        board.player_count += 1;
+
+        let mut occupancy = load_unchecked_mut::<Occupancy>(&ctx.accounts.occupancy)?;
+        let (x, y) = occupancy
+            .find_free(&board.config)
+            .ok_or(GameError::BoardFull)?;
+        occupancy.set_occupied(&board.config, x, y, true);
+
+        let index = board.next_player_index;
+        // Wraps after `u16::MAX` joins, by which time the early players are long gone
+        board.next_player_index = index.checked_add(1).unwrap_or(1);
+        BoardCells::load(&board, &ctx.accounts.board.key(), ctx.remaining_accounts)?
+            .0
+            .set_occupant(x, y, index);
+
         let player = &mut ctx.accounts.player;
         player.authority = ctx.accounts.authority.key();
-        player.x = INITIAL_X;
-        player.y = INITIAL_Y;
+        player.board = ctx.accounts.board.key();
+        player.index = index;
+        player.x = x;
+        player.y = y;
         player.bump = ctx.bumps.player;
-        player.session_key = None;
+        player.sessions = Vec::new();
+        player.delegation = DelegationStatus::default();
+        player.move_nonce = 0;
+        player.move_cooldown = 0;
+        player.velocity_x = 0;
+        player.velocity_y = 0;
+        player.last_update_slot = board.active_slot(Clock::get()?.slot);
+        board.store(&ctx.accounts.board)?;
 
+        emit!(PlayerJoined {
+            board: ctx.accounts.board.key(),
+            player: player.key(),
+            authority: player.authority,
+            x,
+            y,
+        });
         msg!(
-            "Player {} joined at position ({}, {})",
+            "Player {} joined board {} at position ({}, {})",
             player.authority,
+            board.id,
             player.x,
             player.y
         );
         Ok(())
     }
 
+    pub fn leave_game(ctx: Context<LeaveGame>) -> Result<()> {
+        let board = &mut ctx.accounts.board;
+        board.player_count -= 1;
+
+        let player = &ctx.accounts.player;
+        ctx.accounts
+            .occupancy
+            .load_mut()?
+            .set_occupied(&board.config, player.x, player.y, false);
+        BoardCells::load(board, &board.key(), ctx.remaining_accounts)?
+            .0
+            .set_occupant(player.x, player.y, 0);
+
+        emit!(PlayerLeft {
+            board: board.key(),
+            player: player.key(),
+            authority: player.authority,
+            kicked: false,
+        });
+        msg!(
+            "Player {} left board {}",
+            ctx.accounts.authority.key(),
+            board.id
+        );
+        Ok(())
+    }
+
     pub fn register_session_key(
         ctx: Context<RegisterSessionKey>,
-        session_key: Pubkey,
+        label: String,
+        valid_for: i64,
+        scope: u16,
+        max_uses: u32,
     ) -> Result<()> {
+        require!(
+            valid_for > 0 && valid_for <= MAX_SESSION_DURATION,
+            GameError::InvalidSessionDuration
+        );
+        require!(
+            scope != 0 && scope & !SCOPE_ALL == 0,
+            GameError::InvalidSessionScope
+        );
+        require!(max_uses > 0, GameError::InvalidSessionUses);
+        require!(
+            label.len() <= MAX_SESSION_LABEL_LEN,
+            GameError::InvalidSessionLabel
+        );
+        require!(
+            !Board::load(&ctx.accounts.board)?.paused,
+            GameError::GamePaused
+        );
+
+        let session_key = ctx.accounts.session_key.key();
+        let player_key = ctx.accounts.player.key();
+        let player = &mut ctx.accounts.player;
+        let now = Clock::get()?.unix_timestamp;
+        let expires_at = now + valid_for;
+
+        // Re-registering a key refreshes it, and dead sessions make room for new ones
+        player.sessions.retain(|session| {
+            if session.key == session_key {
+                return false;
+            }
+            let usable = session.is_usable(now);
+            if !usable {
+                emit!(SessionKeyRevoked {
+                    player: player_key,
+                    session_key: session.key,
+                });
+            }
+            usable
+        });
+        require!(
+            player.sessions.len() < MAX_SESSIONS,
+            GameError::TooManySessions
+        );
+        player.sessions.push(SessionToken {
+            key: session_key,
+            label: label.clone(),
+            expires_at,
+            scope,
+            uses_remaining: max_uses,
+        });
+
+        emit!(SessionKeyRegistered {
+            player: player.key(),
+            authority: player.authority,
+            session_key,
+            label,
+            expires_at,
+            scope,
+            max_uses,
+        });
+        msg!(
+            "Session key {} registered for player {} until {}",
+            session_key,
+            player.authority,
+            expires_at
+        );
+        Ok(())
+    }
+
+    /// Sends a session key up to `MAX_SESSION_TOP_UP` lamports for its fees. The
+    /// authority's SOL lives on the base layer, so this is sent there, apart from
+    /// `register_session_key`, which may run in the ER.
+    pub fn fund_session_key(ctx: Context<FundSessionKey>, lamports: u64) -> Result<()> {
+        require!(
+            lamports <= MAX_SESSION_TOP_UP,
+            GameError::SessionTopUpTooLarge
+        );
+
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.session_key.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+
+        msg!(
+            "Session key {} funded with {} lamports",
+            ctx.accounts.session_key.key(),
+            lamports
+        );
+        Ok(())
+    }
+
+    pub fn revoke_session_key(ctx: Context<RevokeSessionKey>, session_key: Pubkey) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let signer = ctx.accounts.signer.key();
         let player = &mut ctx.accounts.player;
-        player.session_key = Some(session_key);
 
+        let index = player
+            .sessions
+            .iter()
+            .position(|session| session.key == session_key)
+            .ok_or(GameError::SessionNotFound)?;
+        // Besides the authority, the session key may revoke itself, and anyone may
+        // clean up a session that can no longer be used
+        let allowed = signer == player.authority
+            || signer == session_key
+            || !player.sessions[index].is_usable(now);
+        require!(allowed, GameError::UnauthorizedSigner);
+        player.sessions.remove(index);
+
+        emit!(SessionKeyRevoked {
+            player: player.key(),
+            session_key,
+        });
         msg!(
-            "Session key {} registered for player {}",
+            "Session key {} revoked for player {}",
             session_key,
             player.authority
         );
         Ok(())
     }
 
-    pub fn revoke_session_key(ctx: Context<RevokeSessionKey>) -> Result<()> {
+    /// Revokes the signing session key, if still registered, and returns its whole
+    /// balance to the player's authority. Runs on the base layer, so the player
+    /// must not be delegated.
+    pub fn reclaim_session_funds(ctx: Context<ReclaimSessionFunds>) -> Result<()> {
+        let session_key = ctx.accounts.session_key.key();
         let player = &mut ctx.accounts.player;
-        player.session_key = None;
+        let registered = player.sessions.len();
+        player.sessions.retain(|session| session.key != session_key);
+        if player.sessions.len() < registered {
+            emit!(SessionKeyRevoked {
+                player: player.key(),
+                session_key,
+            });
+        }
+
+        let lamports = ctx.accounts.session_key.lamports();
+        if lamports > 0 {
+            transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.session_key.to_account_info(),
+                        to: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                lamports,
+            )?;
+        }
 
-        msg!("Session key revoked for player {}", player.authority);
+        msg!(
+            "Session key {} returned {} lamports to {}",
+            session_key,
+            lamports,
+            player.authority
+        );
         Ok(())
     }
 
-    pub fn move_player(ctx: Context<MovePlayer>, x_direction: i8, y_direction: i8) -> Result<()> {
+    pub fn revoke_all_sessions(ctx: Context<RevokeAllSessions>) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
         let player = &mut ctx.accounts.player;
+        for session in player.sessions.drain(..) {
+            emit!(SessionKeyRevoked {
+                player: player_key,
+                session_key: session.key,
+            });
+        }
 
-        let new_x = (player.x as i16 + x_direction as i16)
-            .max(0)
-            .min(BOARD_SIZE as i16 - 1) as u8;
+        msg!("All session keys revoked for player {}", player.authority);
+        Ok(())
+    }
 
-        let new_y = (player.y as i16 + y_direction as i16)
-            .max(0)
-            .min(BOARD_SIZE as i16 - 1) as u8;
+    pub fn move_player(
+        ctx: Context<MovePlayer>,
+        x_direction: i8,
+        y_direction: i8,
+        move_nonce: u64,
+    ) -> Result<()> {
+        let player = &mut ctx.accounts.player;
+        player.authorize(
+            &ctx.accounts.signer.key(),
+            SCOPE_MOVE_PLAYER,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let board = Board::load(&ctx.accounts.board)?;
+        require!(!board.paused, GameError::GamePaused);
+        let config = &board.config;
+        player.begin_move(config, move_nonce)?;
 
-        player.x = new_x;
-        player.y = new_y;
+        let mut occupancy = load_unchecked_mut::<Occupancy>(&ctx.accounts.occupancy)?;
+        let (mut cells, _) =
+            BoardCells::load(&board, &ctx.accounts.board.key(), ctx.remaining_accounts)?;
+        player.integrate(
+            config,
+            &mut occupancy,
+            &mut cells,
+            board.active_slot(Clock::get()?.slot),
+        );
+        let (from_x, from_y) = (player.x, player.y);
+        player.step(config, &mut occupancy, &mut cells, x_direction, y_direction)?;
 
+        emit!(PlayerMoved {
+            player: player.key(),
+            authority: player.authority,
+            from_x,
+            from_y,
+            x: player.x,
+            y: player.y,
+            move_nonce,
+        });
         msg!(
             "Player {} moved to position ({}, {})",
             player.authority,
@@ -84,144 +533,1784 @@ pub mod test_2 {
         Ok(())
     }
 
-    pub fn delegate_player(ctx: Context<DelegatePlayer>) -> Result<()> {
+    /// Applies a sequence of steps as a single move. Every step is checked as if
+    /// it were its own `move_player`, and any invalid step fails the whole path.
+    pub fn move_path(ctx: Context<MovePlayer>, steps: Vec<Step>, move_nonce: u64) -> Result<()> {
+        require!(
+            !steps.is_empty() && steps.len() <= MAX_PATH_STEPS,
+            GameError::InvalidPath
+        );
+
+        let player = &mut ctx.accounts.player;
+        player.authorize(
+            &ctx.accounts.signer.key(),
+            SCOPE_MOVE_PLAYER,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let board = Board::load(&ctx.accounts.board)?;
+        require!(!board.paused, GameError::GamePaused);
+        let config = &board.config;
+        player.begin_move(config, move_nonce)?;
+
+        let mut occupancy = load_unchecked_mut::<Occupancy>(&ctx.accounts.occupancy)?;
+        let (mut cells, _) =
+            BoardCells::load(&board, &ctx.accounts.board.key(), ctx.remaining_accounts)?;
+        player.integrate(
+            config,
+            &mut occupancy,
+            &mut cells,
+            board.active_slot(Clock::get()?.slot),
+        );
+        let (from_x, from_y) = (player.x, player.y);
+        for step in &steps {
+            player.step(
+                config,
+                &mut occupancy,
+                &mut cells,
+                step.x_direction,
+                step.y_direction,
+            )?;
+        }
+
+        emit!(PlayerMoved {
+            player: player.key(),
+            authority: player.authority,
+            from_x,
+            from_y,
+            x: player.x,
+            y: player.y,
+            move_nonce,
+        });
+        msg!(
+            "Player {} moved {} steps to position ({}, {})",
+            player.authority,
+            steps.len(),
+            player.x,
+            player.y
+        );
+        Ok(())
+    }
+
+    /// Sets the heading and speed the player drifts at. The position travelled so
+    /// far is integrated first, and the new velocity starts counting from now.
+    pub fn set_velocity(
+        ctx: Context<MovePlayer>,
+        velocity_x: i8,
+        velocity_y: i8,
+        move_nonce: u64,
+    ) -> Result<()> {
+        let player = &mut ctx.accounts.player;
+        let clock = Clock::get()?;
+        player.authorize(
+            &ctx.accounts.signer.key(),
+            SCOPE_MOVE_PLAYER,
+            clock.unix_timestamp,
+        )?;
+        let board = Board::load(&ctx.accounts.board)?;
+        require!(!board.paused, GameError::GamePaused);
+        let config = &board.config;
+        player.begin_move(config, move_nonce)?;
+        config.check_step(velocity_x, velocity_y)?;
+
+        let active_slot = board.active_slot(clock.slot);
+        player.integrate(
+            config,
+            &mut *load_unchecked_mut::<Occupancy>(&ctx.accounts.occupancy)?,
+            &mut BoardCells::load(&board, &ctx.accounts.board.key(), ctx.remaining_accounts)?.0,
+            active_slot,
+        );
+        player.velocity_x = velocity_x;
+        player.velocity_y = velocity_y;
+        player.last_update_slot = active_slot;
+
+        emit!(PlayerVelocityChanged {
+            player: player.key(),
+            x: player.x,
+            y: player.y,
+            velocity_x,
+            velocity_y,
+            slot: clock.slot,
+        });
+        msg!(
+            "Player {} heading ({}, {}) from ({}, {})",
+            player.authority,
+            velocity_x,
+            velocity_y,
+            player.x,
+            player.y
+        );
+        Ok(())
+    }
+
+    /// Advances the board by one tick and applies time-based effects to the
+    /// players passed as writable remaining accounts. Meant to be cranked by the
+    /// board authority in the ER while the board and its players are delegated,
+    /// and rejected while the board is paused.
+    pub fn tick<'info>(ctx: Context<'_, '_, 'info, 'info, Tick<'info>>) -> Result<()> {
+        let board = &mut ctx.accounts.board;
+        board.tick += 1;
+
+        let slot = board.active_slot(Clock::get()?.slot);
+        let mut occupancy = ctx.accounts.occupancy.load_mut()?;
+        let (mut cells, players) = BoardCells::load(board, &board.key(), ctx.remaining_accounts)?;
+        // Loading each account checks it is a Player owned by this program
+        for info in players {
+            let mut player = Account::<Player>::try_from(info)?;
+            require_keys_eq!(player.board, board.key(), GameError::PlayerNotOnBoard);
+            player.move_cooldown = player.move_cooldown.saturating_sub(1);
+            player.integrate(&board.config, &mut occupancy, &mut cells, slot);
+            player.exit(&crate::ID)?;
+        }
+
+        emit!(BoardTicked {
+            board: board.key(),
+            tick: board.tick,
+            players: players.len() as u16,
+        });
+        msg!("Board {} advanced to tick {}", board.id, board.tick);
+        Ok(())
+    }
+
+    /// Delegates the player to an allow-listed ER validator. `time_limit` is not
+    /// passed to the delegation program, which has no notion of it, so the rollup
+    /// never undelegates on its own: the player stays delegated until someone
+    /// calls `undelegate_player`, which anyone may do once `expires_at` has passed.
+    ///
+    /// Every move writes the board's occupancy and open state chunks, so the board
+    /// authority has to start the match with `delegate_occupancy` and
+    /// `delegate_board_state` before players can enter the ER; until then
+    /// delegating a player is rejected rather than leaving it stuck. The chunks
+    /// are passed as remaining accounts after the validator. For the same reason
+    /// a drifting player can't be integrated here, so it has to stop with
+    /// `set_velocity` before the match starts.
+    pub fn delegate_player(
+        ctx: Context<DelegatePlayer>,
+        commit_frequency_ms: u32,
+        time_limit: i64,
+    ) -> Result<()> {
+        // The board may already be in the ER, so read its last committed state
+        let board_data = Board::load_committed(&ctx.accounts.board)?;
+        let config = &board_data.config;
+        require!(
+            (config.min_commit_frequency_ms..=config.max_commit_frequency_ms)
+                .contains(&commit_frequency_ms),
+            GameError::InvalidDelegationConfig
+        );
+        require!(
+            time_limit > 0 && time_limit <= config.max_delegation_time,
+            GameError::InvalidDelegationConfig
+        );
+
+        let validator = board_data.allowed_validator(ctx.remaining_accounts)?;
+
         let authority = ctx.accounts.authority.key();
+        let board = ctx.accounts.board.key();
+
+        // Record the delegation before the account is handed to the delegation
+        // program, which snapshots the current data as the delegated state
+        let clock = Clock::get()?;
+        let expires_at = clock
+            .unix_timestamp
+            .checked_add(time_limit)
+            .ok_or(GameError::InvalidDelegationConfig)?;
+        {
+            let mut data = ctx.accounts.pda.try_borrow_mut_data()?;
+            let mut player = Player::try_deserialize(&mut &data[..])?;
+            require_keys_eq!(player.board, board, GameError::PlayerNotOnBoard);
+            // The ER counts slots on its own, so drift can't carry across
+            require!(
+                (player.velocity_x, player.velocity_y) == (0, 0),
+                GameError::PlayerMoving
+            );
+            player.delegation.delegated = true;
+            player.delegation.validator = Some(validator);
+            player.delegation.delegated_at = clock.slot;
+            player.delegation.expires_at = expires_at;
+            player.try_serialize(&mut &mut data[..])?;
+        }
+        require_keys_eq!(
+            *ctx.accounts.occupancy.owner,
+            DelegationProgram::id(),
+            GameError::OccupancyNotDelegated
+        );
+        board_data.check_board_state_delegated(&board, &ctx.remaining_accounts[1..])?;
+
         ctx.accounts.delegate_pda(
             &ctx.accounts.payer,
-            &[b"player", authority.as_ref()],
+            &[b"player", board.as_ref(), authority.as_ref()],
             DelegateConfig {
-                // Optional: specify ER validator from remaining accounts
-                validator: ctx.remaining_accounts.first().map(|acc| acc.key()),
-                ..Default::default()
+                commit_frequency_ms,
+                validator: Some(validator),
             },
         )?;
+
+        emit!(PlayerDelegated {
+            player: ctx.accounts.pda.key(),
+            authority,
+            validator,
+            commit_frequency_ms,
+            expires_at,
+        });
         msg!("Player {} delegated to Ephemeral Rollup", authority);
         Ok(())
     }
 
     pub fn commit_player(ctx: Context<CommitPlayer>) -> Result<()> {
+        ctx.accounts.authorize(SCOPE_COMMIT_PLAYER)?;
+
+        let slot = Clock::get()?.slot;
+        let player = &mut ctx.accounts.player;
+        player.delegation.last_commit_slot = slot;
+        // Persist the changes before the commit snapshots the account
+        player.exit(&crate::ID)?;
+
         commit_accounts(
             &ctx.accounts.payer,
             vec![&ctx.accounts.player.to_account_info()],
             &ctx.accounts.magic_context,
             &ctx.accounts.magic_program,
         )?;
+
+        emit!(PlayerCommitted {
+            player: ctx.accounts.player.key(),
+            slot,
+        });
         msg!("Player state committed to base layer");
         Ok(())
     }
 
+    pub fn commit_players<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CommitPlayers<'info>>,
+    ) -> Result<()> {
+        require!(
+            !ctx.remaining_accounts.is_empty(),
+            GameError::NoPlayersToCommit
+        );
+        // Loading each account checks it is a Player owned by this program
+        let slot = Clock::get()?.slot;
+        for info in ctx.remaining_accounts {
+            let mut player = Account::<Player>::try_from(info)?;
+            require_keys_eq!(
+                player.board,
+                ctx.accounts.board.key(),
+                GameError::PlayerNotOnBoard
+            );
+            player.delegation.last_commit_slot = slot;
+            player.exit(&crate::ID)?;
+            emit!(PlayerCommitted {
+                player: info.key(),
+                slot,
+            });
+        }
+
+        commit_accounts(
+            &ctx.accounts.authority,
+            ctx.remaining_accounts.iter().collect(),
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        msg!(
+            "{} player states committed to base layer",
+            ctx.remaining_accounts.len()
+        );
+        Ok(())
+    }
+
     pub fn undelegate_player(ctx: Context<CommitPlayer>) -> Result<()> {
+        ctx.accounts.authorize(SCOPE_UNDELEGATE_PLAYER)?;
+
+        let slot = Clock::get()?.slot;
+        let board = &ctx.accounts.board;
+        let player = &mut ctx.accounts.player;
+        // Settle the drift so far, then stop, since the base layer counts slots
+        // separately from the ER
+        player.integrate(
+            &board.config,
+            &mut *ctx.accounts.occupancy.load_mut()?,
+            &mut BoardCells::load(board, &board.key(), ctx.remaining_accounts)?.0,
+            board.active_slot(slot),
+        );
+        if (player.velocity_x, player.velocity_y) != (0, 0) {
+            player.velocity_x = 0;
+            player.velocity_y = 0;
+            emit!(PlayerVelocityChanged {
+                player: player.key(),
+                x: player.x,
+                y: player.y,
+                velocity_x: 0,
+                velocity_y: 0,
+                slot,
+            });
+        }
+        player.delegation.delegated = false;
+        player.delegation.validator = None;
+        player.delegation.expires_at = 0;
+        player.delegation.last_commit_slot = slot;
+        // Session keys only make sense while playing in the ER, so drop them in
+        // the same snapshot that leaves the rollup
+        player.sessions.clear();
+        // Persist the changes before the commit snapshots the account
+        player.exit(&crate::ID)?;
+
         // Commit and undelegate the account
-        // Note: Session key will be cleared by the frontend after undelegation
         commit_and_undelegate_accounts(
             &ctx.accounts.payer,
             vec![&ctx.accounts.player.to_account_info()],
             &ctx.accounts.magic_context,
             &ctx.accounts.magic_program,
         )?;
+
+        emit!(PlayerUndelegated {
+            player: ctx.accounts.player.key(),
+            slot,
+        });
         msg!("Player undelegated from Ephemeral Rollup");
         Ok(())
     }
-}
-
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Board::INIT_SPACE,
-        seeds = [b"board"],
-        bump
-    )]
-    pub board: Account<'info, Board>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
 
-#[derive(Accounts)]
-pub struct JoinGame<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Player::INIT_SPACE,
-        seeds = [b"player", authority.key().as_ref()],
-        bump
-    )]
-    pub player: Account<'info, Player>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    /// Hands the board to the ER. Once it is delegated, joining, leaving and
+    /// administering the board stop working on the base layer until it is
+    /// undelegated, while players can still be delegated to play on it. Its
+    /// occupancy and state chunks are delegated against the board, so they go
+    /// first.
+    pub fn delegate_board(ctx: Context<DelegateBoard>, board_id: u64) -> Result<()> {
+        let board = Board::try_deserialize(&mut &ctx.accounts.board.try_borrow_data()?[..])?;
+        require_keys_eq!(
+            board.authority,
+            ctx.accounts.authority.key(),
+            GameError::UnauthorizedAuthority
+        );
 
-#[derive(Accounts)]
-pub struct MovePlayer<'info> {
-    #[account(
-        mut,
-        seeds = [b"player", player.authority.as_ref()],
-        bump = player.bump,
-        constraint = signer.key() == player.authority || Some(signer.key()) == player.session_key
-    )]
-    pub player: Account<'info, Player>,
-    pub signer: Signer<'info>,
-}
+        let validator = board.allowed_validator(ctx.remaining_accounts)?;
+        ctx.accounts.delegate_board(
+            &ctx.accounts.payer,
+            &[b"board", &board_id.to_le_bytes()],
+            DelegateConfig {
+                validator: Some(validator),
+                ..Default::default()
+            },
+        )?;
 
-#[derive(Accounts)]
-pub struct RegisterSessionKey<'info> {
-    #[account(
-        mut,
-        seeds = [b"player", authority.key().as_ref()],
-        bump = player.bump,
-        has_one = authority
-    )]
-    pub player: Account<'info, Player>,
-    pub authority: Signer<'info>,
-}
+        emit!(BoardDelegated {
+            board: ctx.accounts.board.key(),
+            board_id,
+            validator,
+        });
+        msg!("Board {} delegated to Ephemeral Rollup", board_id);
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct RevokeSessionKey<'info> {
-    #[account(
-        mut,
-        seeds = [b"player", authority.key().as_ref()],
-        bump = player.bump,
-        has_one = authority
-    )]
-    pub player: Account<'info, Player>,
-    pub authority: Signer<'info>,
-}
+    pub fn commit_board(ctx: Context<CommitBoard>) -> Result<()> {
+        commit_accounts(
+            &ctx.accounts.authority,
+            vec![&ctx.accounts.board.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
 
-#[delegate]
-#[derive(Accounts)]
-pub struct DelegatePlayer<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub authority: Signer<'info>,
-    /// CHECK: Checked by delegate macro
-    #[account(mut, del)]
-    pub pda: AccountInfo<'info>,
-}
+        emit!(BoardCommitted {
+            board: ctx.accounts.board.key(),
+            slot: Clock::get()?.slot,
+        });
+        msg!("Board {} committed to base layer", ctx.accounts.board.id);
+        Ok(())
+    }
 
-#[commit]
-#[derive(Accounts)]
-pub struct CommitPlayer<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut)]
-    pub player: Account<'info, Player>,
-}
+    pub fn undelegate_board(ctx: Context<CommitBoard>) -> Result<()> {
+        commit_and_undelegate_accounts(
+            &ctx.accounts.authority,
+            vec![&ctx.accounts.board.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+
+        emit!(BoardUndelegated {
+            board: ctx.accounts.board.key(),
+            slot: Clock::get()?.slot,
+        });
+        msg!(
+            "Board {} undelegated from Ephemeral Rollup",
+            ctx.accounts.board.id
+        );
+        Ok(())
+    }
+
+    pub fn init_board_state(ctx: Context<InitBoardState>, chunk: u16) -> Result<()> {
+        require!(
+            chunk < BOARD_STATE_CHUNKS,
+            GameError::InvalidBoardStateChunk
+        );
+        // A new chunk can't know who already stands on its cells
+        require!(
+            ctx.accounts.board.player_count == 0,
+            GameError::BoardNotEmpty
+        );
+
+        let mut board_state = ctx.accounts.board_state.load_init()?;
+        board_state.board = ctx.accounts.board.key();
+        board_state.chunk = chunk;
+        ctx.accounts.board.board_state_chunks += 1;
+
+        msg!(
+            "Board state chunk {} of board {} initialized",
+            chunk,
+            ctx.accounts.board.id
+        );
+        Ok(())
+    }
+
+    pub fn close_board_state(ctx: Context<CloseBoardState>, chunk: u16) -> Result<()> {
+        ctx.accounts.board.board_state_chunks -= 1;
+
+        msg!(
+            "Board state chunk {} of board {} closed",
+            chunk,
+            ctx.accounts.board.id
+        );
+        Ok(())
+    }
+
+    /// Sets the terrain and item of a cell, leaving its occupant to the game.
+    /// Works wherever the chunk lives, so the authority can reshape the world
+    /// mid-match in the ER.
+    pub fn set_cell(
+        ctx: Context<SetCell>,
+        chunk: u16,
+        x: u8,
+        y: u8,
+        terrain: u8,
+        item: u8,
+    ) -> Result<()> {
+        let config = &ctx.accounts.board.config;
+        require!(x < config.width && y < config.height, GameError::OutOfBounds);
+        require!(
+            (y / BOARD_STATE_ROWS) as u16 == chunk,
+            GameError::InvalidBoardStateChunk
+        );
+
+        let mut board_state = ctx.accounts.board_state.load_mut()?;
+        let cell = &mut board_state.cells[BoardState::cell_index(x, y)];
+        cell.terrain = terrain;
+        cell.item = item;
+
+        emit!(CellUpdated {
+            board: ctx.accounts.board.key(),
+            x,
+            y,
+            terrain,
+            item,
+        });
+        msg!(
+            "Cell ({}, {}) of board {} set to terrain {} and item {}",
+            x,
+            y,
+            ctx.accounts.board.id,
+            terrain,
+            item
+        );
+        Ok(())
+    }
+
+    pub fn delegate_board_state(ctx: Context<DelegateBoardState>, chunk: u16) -> Result<()> {
+        let validator = ctx
+            .accounts
+            .board
+            .allowed_validator(ctx.remaining_accounts)?;
+        let board = ctx.accounts.board.key();
+        ctx.accounts.delegate_board_state(
+            &ctx.accounts.payer,
+            &[b"board_state", board.as_ref(), &chunk.to_le_bytes()],
+            DelegateConfig {
+                validator: Some(validator),
+                ..Default::default()
+            },
+        )?;
+        msg!(
+            "Board state chunk {} of board {} delegated to Ephemeral Rollup",
+            chunk,
+            board
+        );
+        Ok(())
+    }
+
+    pub fn commit_board_state(ctx: Context<CommitBoardState>, chunk: u16) -> Result<()> {
+        commit_accounts(
+            &ctx.accounts.authority,
+            vec![&ctx.accounts.board_state.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        msg!("Board state chunk {} committed to base layer", chunk);
+        Ok(())
+    }
+
+    pub fn undelegate_board_state(ctx: Context<CommitBoardState>, chunk: u16) -> Result<()> {
+        commit_and_undelegate_accounts(
+            &ctx.accounts.authority,
+            vec![&ctx.accounts.board_state.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        msg!(
+            "Board state chunk {} undelegated from Ephemeral Rollup",
+            chunk
+        );
+        Ok(())
+    }
+
+    pub fn delegate_occupancy(ctx: Context<DelegateOccupancy>) -> Result<()> {
+        let validator = ctx
+            .accounts
+            .board
+            .allowed_validator(ctx.remaining_accounts)?;
+        let board = ctx.accounts.board.key();
+        ctx.accounts.delegate_occupancy(
+            &ctx.accounts.payer,
+            &[b"occupancy", board.as_ref()],
+            DelegateConfig {
+                validator: Some(validator),
+                ..Default::default()
+            },
+        )?;
+        msg!("Occupancy of board {} delegated to Ephemeral Rollup", board);
+        Ok(())
+    }
+
+    pub fn commit_occupancy(ctx: Context<CommitOccupancy>) -> Result<()> {
+        commit_accounts(
+            &ctx.accounts.authority,
+            vec![&ctx.accounts.occupancy.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        msg!("Occupancy committed to base layer");
+        Ok(())
+    }
+
+    pub fn undelegate_occupancy(ctx: Context<CommitOccupancy>) -> Result<()> {
+        commit_and_undelegate_accounts(
+            &ctx.accounts.authority,
+            vec![&ctx.accounts.occupancy.to_account_info()],
+            &ctx.accounts.magic_context,
+            &ctx.accounts.magic_program,
+        )?;
+        msg!("Occupancy undelegated from Ephemeral Rollup");
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(board_id: u64)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Board::INIT_SPACE,
+        seeds = [b"board", board_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub board: Account<'info, Board>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<Occupancy>(),
+        seeds = [b"occupancy", board.key().as_ref()],
+        bump
+    )]
+    pub occupancy: AccountLoader<'info, Occupancy>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBoard<'info> {
+    #[account(
+        mut,
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    pub authority: Signer<'info>,
+}
+
+/// The board's open `BoardState` chunks are passed as writable remaining accounts.
+#[derive(Accounts)]
+pub struct KickPlayer<'info> {
+    #[account(
+        mut,
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    #[account(
+        mut,
+        close = player_authority,
+        seeds = [b"player", board.key().as_ref(), player_authority.key().as_ref()],
+        bump = player.bump,
+        has_one = board @ GameError::PlayerNotOnBoard,
+        constraint = !player.delegation.delegated @ GameError::PlayerDelegated
+    )]
+    pub player: Account<'info, Player>,
+    /// Receives the kicked player's rent.
+    #[account(mut, address = player.authority)]
+    pub player_authority: SystemAccount<'info>,
+    #[account(mut, seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: AccountLoader<'info, Occupancy>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBoard<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"occupancy", board.key().as_ref()],
+        bump
+    )]
+    pub occupancy: AccountLoader<'info, Occupancy>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+/// The board's open `BoardState` chunks are passed as writable remaining accounts.
+#[derive(Accounts)]
+pub struct JoinGame<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Player::INIT_SPACE,
+        seeds = [b"player", board.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub player: Account<'info, Player>,
+    /// CHECK: Loaded with `Board::load` in the handler
+    #[account(mut, constraint = !board.data_is_empty() @ GameError::BoardNotInitialized)]
+    pub board: UncheckedAccount<'info>,
+    /// CHECK: Loaded in the handler, since an `AccountLoader` would fail on the
+    /// occupancy of a missing board before the board itself is checked
+    #[account(mut, seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The board's open `BoardState` chunks are passed as writable remaining accounts.
+#[derive(Accounts)]
+pub struct LeaveGame<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"player", board.key().as_ref(), authority.key().as_ref()],
+        bump = player.bump,
+        has_one = authority @ GameError::UnauthorizedSigner,
+        has_one = board @ GameError::PlayerNotOnBoard,
+        constraint = !player.delegation.delegated @ GameError::PlayerDelegated
+    )]
+    pub player: Account<'info, Player>,
+    #[account(mut, seeds = [b"board", board.id.to_le_bytes().as_ref()], bump = board.bump)]
+    pub board: Account<'info, Board>,
+    #[account(mut, seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: AccountLoader<'info, Occupancy>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+/// The board's open `BoardState` chunks are passed as writable remaining accounts.
+#[derive(Accounts)]
+pub struct MovePlayer<'info> {
+    /// CHECK: Loaded with `Board::load` in the handler
+    #[account(constraint = !board.data_is_empty() @ GameError::BoardNotInitialized)]
+    pub board: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"player", board.key().as_ref(), player.authority.as_ref()],
+        bump = player.bump,
+        has_one = board @ GameError::PlayerNotOnBoard,
+        constraint = signer.key() == player.authority
+            || player.sessions.iter().any(|session| session.key == signer.key())
+            @ GameError::UnauthorizedSigner
+    )]
+    pub player: Account<'info, Player>,
+    /// CHECK: Loaded in the handler, like the board
+    #[account(mut, seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: UncheckedAccount<'info>,
+    pub signer: Signer<'info>,
+}
+
+/// The board's open `BoardState` chunks are passed as writable remaining accounts,
+/// followed by the players to tick.
+#[derive(Accounts)]
+pub struct Tick<'info> {
+    #[account(
+        mut,
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority,
+        constraint = !board.paused @ GameError::GamePaused
+    )]
+    pub board: Account<'info, Board>,
+    #[account(mut, seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: AccountLoader<'info, Occupancy>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterSessionKey<'info> {
+    /// CHECK: Loaded with `Board::load` in the handler
+    #[account(constraint = !board.data_is_empty() @ GameError::BoardNotInitialized)]
+    pub board: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"player", board.key().as_ref(), authority.key().as_ref()],
+        bump = player.bump,
+        has_one = authority @ GameError::UnauthorizedSigner,
+        has_one = board @ GameError::PlayerNotOnBoard
+    )]
+    pub player: Account<'info, Player>,
+    pub authority: Signer<'info>,
+    /// Co-signs to prove the authority is binding a key somebody holds.
+    pub session_key: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FundSessionKey<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub session_key: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSessionKey<'info> {
+    #[account(
+        mut,
+        seeds = [b"player", player.board.as_ref(), player.authority.as_ref()],
+        bump = player.bump
+    )]
+    pub player: Account<'info, Player>,
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimSessionFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"player", player.board.as_ref(), authority.key().as_ref()],
+        bump = player.bump,
+        has_one = authority @ GameError::UnauthorizedSigner
+    )]
+    pub player: Account<'info, Player>,
+    #[account(mut)]
+    pub authority: SystemAccount<'info>,
+    #[account(mut)]
+    pub session_key: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeAllSessions<'info> {
+    #[account(
+        mut,
+        seeds = [b"player", player.board.as_ref(), authority.key().as_ref()],
+        bump = player.bump,
+        has_one = authority @ GameError::UnauthorizedSigner
+    )]
+    pub player: Account<'info, Player>,
+    pub authority: Signer<'info>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegatePlayer<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    /// CHECK: May already be owned by the rollup, so it is read in the handler
+    /// and matched against the player
+    pub board: UncheckedAccount<'info>,
+    /// CHECK: Only its owner is read, in the handler
+    #[account(seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: UncheckedAccount<'info>,
+    /// CHECK: Checked by delegate macro
+    #[account(mut, del, constraint = pda.owner == &crate::ID @ GameError::PlayerDelegated)]
+    pub pda: AccountInfo<'info>,
+}
+
+/// `undelegate_player` also takes the board's open `BoardState` chunks as
+/// writable remaining accounts.
+#[commit]
+#[derive(Accounts)]
+pub struct CommitPlayer<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"board", board.id.to_le_bytes().as_ref()], bump = board.bump)]
+    pub board: Account<'info, Board>,
+    #[account(
+        mut,
+        seeds = [b"player", board.key().as_ref(), player.authority.as_ref()],
+        bump = player.bump,
+        has_one = board @ GameError::PlayerNotOnBoard
+    )]
+    pub player: Account<'info, Player>,
+    /// Written when undelegating, to settle the player's drift.
+    #[account(mut, seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: AccountLoader<'info, Occupancy>,
+}
+
+impl CommitPlayer<'_> {
+    /// The board authority may always commit, and anyone may undelegate a player
+    /// whose delegation time limit has passed; everyone else goes through the
+    /// player's own signer checks.
+    fn authorize(&mut self, scope: u16) -> Result<()> {
+        let payer = self.payer.key();
+        let now = Clock::get()?.unix_timestamp;
+        let expired = self.player.delegation.delegated && now >= self.player.delegation.expires_at;
+        if payer == self.board.authority || (scope == SCOPE_UNDELEGATE_PLAYER && expired) {
+            return Ok(());
+        }
+        self.player.authorize(&payer, scope, now)
+    }
+}
+
+/// Players to commit are passed as writable remaining accounts.
+#[commit]
+#[derive(Accounts)]
+pub struct CommitPlayers<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateOccupancy<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    /// CHECK: Checked by delegate macro
+    #[account(mut, del, seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: AccountInfo<'info>,
+}
+
+#[commit]
+#[derive(Accounts)]
+pub struct CommitOccupancy<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    #[account(mut, seeds = [b"occupancy", board.key().as_ref()], bump)]
+    pub occupancy: AccountLoader<'info, Occupancy>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+#[instruction(board_id: u64)]
+pub struct DelegateBoard<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    /// CHECK: Checked by delegate macro, authority checked in the handler
+    #[account(
+        mut,
+        del,
+        seeds = [b"board", board_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = board.owner == &crate::ID @ GameError::BoardDelegated
+    )]
+    pub board: AccountInfo<'info>,
+}
+
+#[commit]
+#[derive(Accounts)]
+pub struct CommitBoard<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+}
+
+#[derive(Accounts)]
+#[instruction(chunk: u16)]
+pub struct InitBoardState<'info> {
+    #[account(
+        mut,
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<BoardState>(),
+        seeds = [b"board_state", board.key().as_ref(), chunk.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub board_state: AccountLoader<'info, BoardState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(chunk: u16)]
+pub struct CloseBoardState<'info> {
+    #[account(
+        mut,
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"board_state", board.key().as_ref(), chunk.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub board_state: AccountLoader<'info, BoardState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(chunk: u16)]
+pub struct SetCell<'info> {
+    #[account(
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    #[account(
+        mut,
+        seeds = [b"board_state", board.key().as_ref(), chunk.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub board_state: AccountLoader<'info, BoardState>,
+    pub authority: Signer<'info>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+#[instruction(chunk: u16)]
+pub struct DelegateBoardState<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    /// CHECK: Checked by delegate macro
+    #[account(
+        mut,
+        del,
+        seeds = [b"board_state", board.key().as_ref(), chunk.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub board_state: AccountInfo<'info>,
+}
+
+#[commit]
+#[derive(Accounts)]
+#[instruction(chunk: u16)]
+pub struct CommitBoardState<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"board", board.id.to_le_bytes().as_ref()],
+        bump = board.bump,
+        has_one = authority @ GameError::UnauthorizedAuthority
+    )]
+    pub board: Account<'info, Board>,
+    #[account(
+        mut,
+        seeds = [b"board_state", board.key().as_ref(), chunk.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub board_state: AccountLoader<'info, BoardState>,
+}
 
 #[account]
 #[derive(InitSpace)]
 pub struct Board {
+    pub id: u64,
     pub authority: Pubkey,
+    pub config: BoardConfig,
+    pub player_count: u16,
+    pub paused: bool,
+    /// Ephemeral Rollup validators players may delegate to.
+    #[max_len(MAX_VALIDATORS)]
+    pub allowed_validators: Vec<Pubkey>,
+    /// Ticks advanced by the crank since the board was created.
+    pub tick: u64,
+    /// `BoardState` chunks initialized and not yet closed.
+    pub board_state_chunks: u16,
+    /// Slot the board was last paused at.
+    pub paused_at_slot: u64,
+    /// Total slots the board has spent paused, not counting a pause in progress.
+    pub paused_slots: u64,
+    /// `Player::index` handed to the next player to join. Zero marks an empty
+    /// cell, so indices start at one.
+    pub next_player_index: u16,
+    pub bump: u8,
+}
+
+impl Board {
+    /// Reads a board owned by this program. Callers take the account unchecked so
+    /// an address no board was created at fails with `BoardNotInitialized`
+    /// instead of Anchor's generic error; since boards are only ever created at
+    /// their PDA, the owner and discriminator checks here stand in for the seeds.
+    pub fn load(info: &AccountInfo) -> Result<Board> {
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+        Board::try_deserialize(&mut &info.try_borrow_data()?[..])
+    }
+
+    /// Writes back a board read with `load`.
+    pub fn store(&self, info: &AccountInfo) -> Result<()> {
+        self.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+    }
+
+    /// Reads a board whether or not it is delegated. While it is in the ER, the
+    /// data left on the base layer is its last committed state.
+    pub fn load_committed(info: &AccountInfo) -> Result<Board> {
+        require!(
+            info.owner == &crate::ID || info.owner == &DelegationProgram::id(),
+            ErrorCode::AccountOwnedByWrongProgram
+        );
+        Board::try_deserialize(&mut &info.try_borrow_data()?[..])
+    }
+
+    /// Checks `chunks` are all of the board's open `BoardState` chunks and that
+    /// each is owned by the delegation program. A chunk's data stays in place
+    /// while it is delegated, so its index is read from there and the address
+    /// rederived from it.
+    pub fn check_board_state_delegated(&self, board: &Pubkey, chunks: &[AccountInfo]) -> Result<()> {
+        require!(
+            chunks.len() == self.board_state_chunks as usize,
+            GameError::BoardStateMissing
+        );
+        let mut seen = Vec::with_capacity(chunks.len());
+        for info in chunks {
+            require_keys_eq!(
+                *info.owner,
+                DelegationProgram::id(),
+                GameError::BoardStateNotDelegated
+            );
+            let data = info.try_borrow_data()?;
+            let chunk_offset = BoardState::DISCRIMINATOR.len() + 32;
+            let chunk = data
+                .get(chunk_offset..chunk_offset + 2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .ok_or(GameError::BoardStateMissing)?;
+            let (address, _) = Pubkey::find_program_address(
+                &[b"board_state", board.as_ref(), &chunk.to_le_bytes()],
+                &crate::ID,
+            );
+            require!(
+                address == info.key() && !seen.contains(&chunk),
+                GameError::BoardStateMissing
+            );
+            seen.push(chunk);
+        }
+        Ok(())
+    }
+
+    /// The ER validator passed as the first remaining account, which must be on
+    /// the allow-list so the board, its state and its players all end up on a
+    /// validator the authority approved.
+    pub fn allowed_validator(&self, remaining_accounts: &[AccountInfo]) -> Result<Pubkey> {
+        remaining_accounts
+            .first()
+            .map(|acc| acc.key())
+            .filter(|validator| self.allowed_validators.contains(validator))
+            .ok_or(error!(GameError::ValidatorNotAllowed))
+    }
+
+    /// Slots the board has spent unpaused, which stands still while it is paused.
+    /// Velocity is integrated against this clock so players don't drift through
+    /// a pause.
+    pub fn active_slot(&self, slot: u64) -> u64 {
+        if self.paused {
+            self.paused_at_slot.saturating_sub(self.paused_slots)
+        } else {
+            slot.saturating_sub(self.paused_slots)
+        }
+    }
+}
+
+/// Dimensions and rules of a board, chosen by its authority at initialization.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct BoardConfig {
+    pub width: u8,
+    pub height: u8,
+    pub spawn_x: u8,
+    pub spawn_y: u8,
+    pub max_step: u8,
+    pub max_players: u16,
+    pub movement_mode: MovementMode,
+    /// Bounds on the commit frequency players may request when delegating.
+    pub min_commit_frequency_ms: u32,
+    pub max_commit_frequency_ms: u32,
+    /// Longest time a player may stay delegated, in seconds.
+    pub max_delegation_time: i64,
+    /// Ticks a player must wait between moves; zero disables the cooldown.
+    pub move_cooldown_ticks: u16,
+}
+
+impl BoardConfig {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            (1..=MAX_BOARD_SIZE).contains(&self.width)
+                && (1..=MAX_BOARD_SIZE).contains(&self.height),
+            GameError::InvalidBoardConfig
+        );
+        require!(
+            self.spawn_x < self.width && self.spawn_y < self.height,
+            GameError::InvalidBoardConfig
+        );
+        require!(
+            self.max_step > 0 && self.max_players > 0,
+            GameError::InvalidBoardConfig
+        );
+        require!(
+            self.min_commit_frequency_ms <= self.max_commit_frequency_ms
+                && self.max_delegation_time > 0,
+            GameError::InvalidBoardConfig
+        );
+        Ok(())
+    }
+
+    /// Checks a single step, or a velocity, against the max step and movement mode.
+    pub fn check_step(&self, x_direction: i8, y_direction: i8) -> Result<()> {
+        require!(
+            x_direction.unsigned_abs() <= self.max_step
+                && y_direction.unsigned_abs() <= self.max_step,
+            GameError::MoveTooLarge
+        );
+        require!(
+            self.movement_mode.allows(x_direction, y_direction),
+            GameError::InvalidDirection
+        );
+        Ok(())
+    }
+}
+
+/// Which step directions `move_player` accepts, on top of the board's max step.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum MovementMode {
+    /// Any combination of horizontal and vertical offsets.
+    Free,
+    /// Straight or exactly diagonal steps.
+    EightWay,
+    /// Straight steps along a single axis.
+    FourWay,
+}
+
+impl MovementMode {
+    pub fn allows(self, x_direction: i8, y_direction: i8) -> bool {
+        let straight = x_direction == 0 || y_direction == 0;
+        match self {
+            MovementMode::Free => true,
+            MovementMode::EightWay => {
+                straight || x_direction.unsigned_abs() == y_direction.unsigned_abs()
+            }
+            MovementMode::FourWay => straight,
+        }
+    }
+}
+
+/// Borrows a zero-copy account taken unchecked, after the owner and
+/// discriminator checks an `AccountLoader` would have made.
+fn load_unchecked_mut<'a, T: ZeroCopy + Owner>(info: &'a AccountInfo) -> Result<RefMut<'a, T>> {
+    require_keys_eq!(*info.owner, T::owner(), ErrorCode::AccountOwnedByWrongProgram);
+    let data = info.try_borrow_mut_data()?;
+    let end = T::DISCRIMINATOR.len() + std::mem::size_of::<T>();
+    require!(
+        data.len() >= end && data.starts_with(T::DISCRIMINATOR),
+        ErrorCode::AccountDiscriminatorMismatch
+    );
+    Ok(RefMut::map(data, |data| {
+        bytemuck::from_bytes_mut(&mut data[T::DISCRIMINATOR.len()..end])
+    }))
+}
+
+/// One bit per board cell, set while a player stands on it. Joins, leaves and kicks
+/// update it on the base layer; once a match starts it is delegated to the
+/// Ephemeral Rollup alongside the players so moves can update it there.
+#[account(zero_copy)]
+pub struct Occupancy {
+    pub board: Pubkey,
+    pub bits: [u8; OCCUPANCY_BYTES],
+}
+
+impl Occupancy {
+    fn bit(config: &BoardConfig, x: u8, y: u8) -> (usize, u8) {
+        let index = y as usize * config.width as usize + x as usize;
+        (index / 8, 1 << (index % 8))
+    }
+
+    pub fn is_occupied(&self, config: &BoardConfig, x: u8, y: u8) -> bool {
+        let (byte, mask) = Self::bit(config, x, y);
+        self.bits[byte] & mask != 0
+    }
+
+    pub fn set_occupied(&mut self, config: &BoardConfig, x: u8, y: u8, occupied: bool) {
+        let (byte, mask) = Self::bit(config, x, y);
+        if occupied {
+            self.bits[byte] |= mask;
+        } else {
+            self.bits[byte] &= !mask;
+        }
+    }
+
+    /// First free cell at or after the spawn point in row-major order, wrapping
+    /// around the end of the board.
+    pub fn find_free(&self, config: &BoardConfig) -> Option<(u8, u8)> {
+        let width = config.width as usize;
+        let cells = width * config.height as usize;
+        let spawn = config.spawn_y as usize * width + config.spawn_x as usize;
+        (0..cells)
+            .map(|offset| (spawn + offset) % cells)
+            .map(|index| ((index % width) as u8, (index / width) as u8))
+            .find(|&(x, y)| !self.is_occupied(config, x, y))
+    }
+}
+
+/// Per-cell world data shared by everyone on a board. Cells are stored row-major
+/// with a stride of `MAX_BOARD_SIZE`, so row `y` lives in chunk
+/// `y / BOARD_STATE_ROWS`. Cell occupants mirror `Occupancy`, which collisions
+/// are checked against; see `BoardCells` for how they are kept in step.
+///
+/// The full grid of the largest board is about 40KB, over the 10KB limit for
+/// accounts created or delegated through CPI, so it is split into
+/// `BOARD_STATE_CHUNKS` accounts of `BOARD_STATE_ROWS` rows each. Every chunk is
+/// initialized and delegated on its own.
+#[account(zero_copy)]
+pub struct BoardState {
+    pub board: Pubkey,
+    pub chunk: u16,
+    pub cells: [Cell; BOARD_STATE_CELLS],
+}
+
+#[zero_copy]
+pub struct Cell {
+    pub terrain: u8,
+    pub item: u8,
+    /// Game-assigned index of the player on the cell, zero when empty.
+    pub occupant: u16,
+}
+
+impl BoardState {
+    /// Position of the cell at (`x`, `y`) within the chunk holding row `y`.
+    pub fn cell_index(x: u8, y: u8) -> usize {
+        (y % BOARD_STATE_ROWS) as usize * MAX_BOARD_SIZE as usize + x as usize
+    }
+}
+
+/// The open `BoardState` chunks of a board, loaded from the leading remaining
+/// accounts of every instruction that places or moves players so that cell
+/// occupants change together with `Occupancy`. All open chunks are required, as
+/// a path or a drift may cross any row; cells in chunks that were never opened
+/// have nothing to update.
+pub struct BoardCells<'a> {
+    chunks: Vec<RefMut<'a, BoardState>>,
+}
+
+impl<'a> BoardCells<'a> {
+    /// Loads the board's `board_state_chunks` chunks from the front of
+    /// `remaining_accounts`, returning the accounts after them.
+    pub fn load<'info>(
+        board: &Board,
+        board_key: &Pubkey,
+        remaining_accounts: &'a [AccountInfo<'info>],
+    ) -> Result<(Self, &'a [AccountInfo<'info>])> {
+        let count = board.board_state_chunks as usize;
+        require!(
+            remaining_accounts.len() >= count,
+            GameError::BoardStateMissing
+        );
+        let (infos, rest) = remaining_accounts.split_at(count);
+
+        let mut chunks: Vec<RefMut<'a, BoardState>> = Vec::with_capacity(count);
+        for info in infos {
+            let chunk = load_unchecked_mut::<BoardState>(info)?;
+            require!(
+                chunk.board == *board_key && chunks.iter().all(|other| other.chunk != chunk.chunk),
+                GameError::BoardStateMissing
+            );
+            chunks.push(chunk);
+        }
+        Ok((BoardCells { chunks }, rest))
+    }
+
+    /// Records `occupant` on the cell at (`x`, `y`), if its chunk is open.
+    pub fn set_occupant(&mut self, x: u8, y: u8, occupant: u16) {
+        let chunk = (y / BOARD_STATE_ROWS) as u16;
+        if let Some(state) = self.chunks.iter_mut().find(|state| state.chunk == chunk) {
+            state.cells[BoardState::cell_index(x, y)].occupant = occupant;
+        }
+    }
 }
 
 #[account]
 #[derive(InitSpace)]
 pub struct Player {
     pub authority: Pubkey,
+    pub board: Pubkey,
+    /// Written to the `BoardState` cell the player stands on.
+    pub index: u16,
     pub x: u8,
     pub y: u8,
     pub bump: u8,
-    pub session_key: Option<Pubkey>,
+    #[max_len(MAX_SESSIONS)]
+    pub sessions: Vec<SessionToken>,
+    pub delegation: DelegationStatus,
+    /// Number of moves applied so far, counting a path as one move; each move
+    /// must quote it to be accepted.
+    pub move_nonce: u64,
+    /// Ticks left before the player may move again, counted down by `tick`.
+    pub move_cooldown: u16,
+    /// Cells moved every `SLOTS_PER_VELOCITY_STEP` slots; zero when standing still.
+    pub velocity_x: i8,
+    pub velocity_y: i8,
+    /// Board active slot, see `Board::active_slot`, the position was last
+    /// integrated up to.
+    pub last_update_slot: u64,
+}
+
+impl Player {
+    /// Accepts a move quoting the current nonce and advances it, so moves apply
+    /// in the order the client issued them and a replayed move is rejected. The
+    /// player must be off cooldown, and the move starts a new one.
+    pub fn begin_move(&mut self, config: &BoardConfig, move_nonce: u64) -> Result<()> {
+        require!(move_nonce == self.move_nonce, GameError::MoveNonceMismatch);
+        require!(self.move_cooldown == 0, GameError::MoveOnCooldown);
+        self.move_nonce += 1;
+        self.move_cooldown = config.move_cooldown_ticks;
+        Ok(())
+    }
+
+    /// Moves by one step within the board's rules, keeping `occupancy` in sync.
+    /// A step that would leave the board is rejected rather than clamped, which
+    /// would change its direction.
+    pub fn step(
+        &mut self,
+        config: &BoardConfig,
+        occupancy: &mut Occupancy,
+        cells: &mut BoardCells,
+        x_direction: i8,
+        y_direction: i8,
+    ) -> Result<()> {
+        config.check_step(x_direction, y_direction)?;
+
+        let (new_x, new_y) = self
+            .destination(config, x_direction, y_direction)
+            .ok_or(GameError::OutOfBounds)?;
+        if (new_x, new_y) != (self.x, self.y) {
+            require!(
+                !occupancy.is_occupied(config, new_x, new_y),
+                GameError::CellOccupied
+            );
+            self.relocate(config, occupancy, cells, new_x, new_y);
+        }
+        Ok(())
+    }
+
+    /// Advances the player along its velocity by the whole steps elapsed since
+    /// `last_update_slot`, carrying leftover slots into the next update. `slot` is
+    /// the board's active slot, so time spent paused is skipped. Running into an
+    /// edge or an occupied cell halts the player in front of it.
+    pub fn integrate(
+        &mut self,
+        config: &BoardConfig,
+        occupancy: &mut Occupancy,
+        cells: &mut BoardCells,
+        slot: u64,
+    ) {
+        // Slots restart when the player moves between the base layer and the ER
+        if slot < self.last_update_slot || (self.velocity_x, self.velocity_y) == (0, 0) {
+            self.last_update_slot = slot;
+            return;
+        }
+
+        let steps = (slot - self.last_update_slot) / SLOTS_PER_VELOCITY_STEP;
+        self.last_update_slot += steps * SLOTS_PER_VELOCITY_STEP;
+        // Any longer and the player has hit an edge anyway
+        for _ in 0..steps.min(MAX_BOARD_SIZE as u64) {
+            match self.destination(config, self.velocity_x, self.velocity_y) {
+                Some((new_x, new_y)) if !occupancy.is_occupied(config, new_x, new_y) => {
+                    self.relocate(config, occupancy, cells, new_x, new_y);
+                }
+                _ => {
+                    self.velocity_x = 0;
+                    self.velocity_y = 0;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Cell reached by stepping from the current position, or `None` if it is off
+    /// the board.
+    fn destination(
+        &self,
+        config: &BoardConfig,
+        x_direction: i8,
+        y_direction: i8,
+    ) -> Option<(u8, u8)> {
+        let new_x = self.x as i16 + x_direction as i16;
+        let new_y = self.y as i16 + y_direction as i16;
+        let on_board =
+            (0..config.width as i16).contains(&new_x) && (0..config.height as i16).contains(&new_y);
+        on_board.then_some((new_x as u8, new_y as u8))
+    }
+
+    fn relocate(
+        &mut self,
+        config: &BoardConfig,
+        occupancy: &mut Occupancy,
+        cells: &mut BoardCells,
+        x: u8,
+        y: u8,
+    ) {
+        occupancy.set_occupied(config, self.x, self.y, false);
+        occupancy.set_occupied(config, x, y, true);
+        cells.set_occupant(self.x, self.y, 0);
+        cells.set_occupant(x, y, self.index);
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Checks that `signer` may act for this player within `scope`. The authority
+    /// is always allowed; a session key must be registered, unexpired, scoped for
+    /// the instruction and have uses left, and each successful check consumes a use.
+    pub fn authorize(&mut self, signer: &Pubkey, scope: u16, now: i64) -> Result<()> {
+        if *signer == self.authority {
+            return Ok(());
+        }
+
+        let session = self
+            .sessions
+            .iter_mut()
+            .find(|session| session.key == *signer)
+            .ok_or(GameError::UnauthorizedSigner)?;
+        require!(now < session.expires_at, GameError::SessionExpired);
+        require!(session.scope & scope == scope, GameError::SessionOutOfScope);
+        require!(session.uses_remaining > 0, GameError::SessionExhausted);
+
+        session.uses_remaining -= 1;
+        Ok(())
+    }
+}
+
+/// A delegated signing key for a player, limited in time, scope and number of uses.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct SessionToken {
+    pub key: Pubkey,
+    /// Client-chosen name such as the device the key lives on.
+    #[max_len(MAX_SESSION_LABEL_LEN)]
+    pub label: String,
+    pub expires_at: i64,
+    pub scope: u16,
+    pub uses_remaining: u32,
+}
+
+impl SessionToken {
+    pub fn is_usable(&self, now: i64) -> bool {
+        now < self.expires_at && self.uses_remaining > 0
+    }
+}
+
+/// Where a player's state currently lives, so clients know which RPC to use.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct DelegationStatus {
+    pub delegated: bool,
+    /// Ephemeral Rollup validator the player is delegated to.
+    pub validator: Option<Pubkey>,
+    /// Slot of the last delegation.
+    pub delegated_at: u64,
+    /// Unix time after which anyone may undelegate the player.
+    pub expires_at: i64,
+    /// Slot of the last commit back to the base layer.
+    pub last_commit_slot: u64,
+}
+
+/// One step of a `move_path`, with the same limits as a `move_player` move.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct Step {
+    pub x_direction: i8,
+    pub y_direction: i8,
+}
+
+#[event]
+pub struct BoardInitialized {
+    pub board: Pubkey,
+    pub board_id: u64,
+    pub authority: Pubkey,
+    pub config: BoardConfig,
+}
+
+#[event]
+pub struct BoardConfigUpdated {
+    pub board: Pubkey,
+    pub config: BoardConfig,
+}
+
+#[event]
+pub struct BoardPauseChanged {
+    pub board: Pubkey,
+    pub paused: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct BoardAuthorityTransferred {
+    pub board: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+/// Emitted when a validator is added to, or removed from, the board's allow-list.
+#[event]
+pub struct BoardValidatorChanged {
+    pub board: Pubkey,
+    pub validator: Pubkey,
+    pub allowed: bool,
+}
+
+#[event]
+pub struct CellUpdated {
+    pub board: Pubkey,
+    pub x: u8,
+    pub y: u8,
+    pub terrain: u8,
+    pub item: u8,
+}
+
+#[event]
+pub struct BoardClosed {
+    pub board: Pubkey,
+    pub board_id: u64,
+}
+
+#[event]
+pub struct BoardDelegated {
+    pub board: Pubkey,
+    pub board_id: u64,
+    pub validator: Pubkey,
+}
+
+#[event]
+pub struct BoardCommitted {
+    pub board: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct BoardUndelegated {
+    pub board: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PlayerJoined {
+    pub board: Pubkey,
+    pub player: Pubkey,
+    pub authority: Pubkey,
+    pub x: u8,
+    pub y: u8,
+}
+
+/// Emitted when a player leaves a board, or is kicked by the board authority.
+#[event]
+pub struct PlayerLeft {
+    pub board: Pubkey,
+    pub player: Pubkey,
+    pub authority: Pubkey,
+    pub kicked: bool,
+}
+
+#[event]
+pub struct PlayerMoved {
+    pub player: Pubkey,
+    pub authority: Pubkey,
+    pub from_x: u8,
+    pub from_y: u8,
+    pub x: u8,
+    pub y: u8,
+    pub move_nonce: u64,
+}
+
+/// Emitted when a player changes velocity, with the position and slot the new
+/// motion starts from so clients can extrapolate it.
+#[event]
+pub struct PlayerVelocityChanged {
+    pub player: Pubkey,
+    pub x: u8,
+    pub y: u8,
+    pub velocity_x: i8,
+    pub velocity_y: i8,
+    pub slot: u64,
+}
+
+#[event]
+pub struct BoardTicked {
+    pub board: Pubkey,
+    pub tick: u64,
+    pub players: u16,
+}
+
+#[event]
+pub struct SessionKeyRegistered {
+    pub player: Pubkey,
+    pub authority: Pubkey,
+    pub session_key: Pubkey,
+    pub label: String,
+    pub expires_at: i64,
+    pub scope: u16,
+    pub max_uses: u32,
+}
+
+#[event]
+pub struct SessionKeyRevoked {
+    pub player: Pubkey,
+    pub session_key: Pubkey,
+}
+
+#[event]
+pub struct PlayerDelegated {
+    pub player: Pubkey,
+    pub authority: Pubkey,
+    pub validator: Pubkey,
+    pub commit_frequency_ms: u32,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct PlayerCommitted {
+    pub player: Pubkey,
+    pub slot: u64,
+}
+
+/// Emitted when a player leaves the rollup, which also drops its session keys.
+#[event]
+pub struct PlayerUndelegated {
+    pub player: Pubkey,
+    pub slot: u64,
+}
+
+#[error_code]
+pub enum GameError {
+    #[msg("Signer is neither the player authority nor one of its session keys")]
+    UnauthorizedSigner,
+    #[msg("Session key has expired")]
+    SessionExpired,
+    #[msg("Session key is not allowed to sign this instruction")]
+    SessionOutOfScope,
+    #[msg("Session key has no uses left")]
+    SessionExhausted,
+    #[msg("Session duration must be positive and at most one day")]
+    InvalidSessionDuration,
+    #[msg("Session scope must be a non-empty set of known instructions")]
+    InvalidSessionScope,
+    #[msg("Session must allow at least one use")]
+    InvalidSessionUses,
+    #[msg("Move exceeds the board's maximum step size")]
+    MoveTooLarge,
+    #[msg("Move direction is not allowed by the board's movement mode")]
+    InvalidDirection,
+    #[msg("Board configuration is invalid")]
+    InvalidBoardConfig,
+    #[msg("Board has reached its maximum number of players")]
+    BoardFull,
+    #[msg("Player does not belong to this board")]
+    PlayerNotOnBoard,
+    #[msg("Signer is not the board authority")]
+    UnauthorizedAuthority,
+    #[msg("Board still has players")]
+    BoardNotEmpty,
+    #[msg("Game is paused")]
+    GamePaused,
+    #[msg("Target cell is occupied by another player")]
+    CellOccupied,
+    #[msg("Board state chunk index is out of range")]
+    InvalidBoardStateChunk,
+    #[msg("Player is already delegated to an Ephemeral Rollup")]
+    PlayerDelegated,
+    #[msg("Board is already delegated to an Ephemeral Rollup")]
+    BoardDelegated,
+    #[msg("No player accounts were passed to commit")]
+    NoPlayersToCommit,
+    #[msg("Commit frequency or time limit is outside the board's bounds")]
+    InvalidDelegationConfig,
+    #[msg("Validator is not on the board's allow-list")]
+    ValidatorNotAllowed,
+    #[msg("Board's validator allow-list is full")]
+    ValidatorListFull,
+    #[msg("Session label is too long")]
+    InvalidSessionLabel,
+    #[msg("Player already has the maximum number of session keys")]
+    TooManySessions,
+    #[msg("Session key is not registered for this player")]
+    SessionNotFound,
+    #[msg("Session key top-up exceeds the allowed maximum")]
+    SessionTopUpTooLarge,
+    #[msg("Move nonce does not match the player's current nonce")]
+    MoveNonceMismatch,
+    #[msg("Path must have between one and the maximum number of steps")]
+    InvalidPath,
+    #[msg("Player must wait for its move cooldown to expire")]
+    MoveOnCooldown,
+    #[msg("Move would leave the board")]
+    OutOfBounds,
+    #[msg("Board state chunks must be closed before the board")]
+    BoardStateOpen,
+    #[msg("Board has not been initialized")]
+    BoardNotInitialized,
+    #[msg("Board occupancy must be in the Ephemeral Rollup before players join it there")]
+    OccupancyNotDelegated,
+    #[msg("Player must stop moving before it is delegated")]
+    PlayerMoving,
+    #[msg("Every open board state chunk must be passed ahead of other remaining accounts")]
+    BoardStateMissing,
+    #[msg("Board state chunks must be in the Ephemeral Rollup before players join it there")]
+    BoardStateNotDelegated,
 }
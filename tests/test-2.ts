@@ -1,7 +1,7 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Test2 } from "../target/types/test_2";
-import { PublicKey } from "@solana/web3.js";
+import { Keypair, PublicKey } from "@solana/web3.js";
 import { expect } from "chai";
 
 describe("test-2", () => {
@@ -11,79 +11,1006 @@ describe("test-2", () => {
   const program = anchor.workspace.test2 as Program<Test2>;
   const provider = anchor.getProvider();
 
-  const [boardPda] = PublicKey.findProgramAddressSync(
-    [Buffer.from("board")],
-    program.programId
-  );
+  const boardId = new anchor.BN(Date.now());
 
-  const [playerPda] = PublicKey.findProgramAddressSync(
-    [Buffer.from("player"), provider.publicKey.toBuffer()],
-    program.programId
-  );
+  // A player is seeded by its board and its wallet
+  const playerPda = (board: PublicKey, authority: PublicKey = provider.publicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("player"), board.toBuffer(), authority.toBuffer()],
+      program.programId
+    )[0];
+
+  // A board and this wallet's player on it
+  const boardPdas = (id: anchor.BN) => {
+    const [board] = PublicKey.findProgramAddressSync(
+      [Buffer.from("board"), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    return { board, player: playerPda(board) };
+  };
+
+  // Moves must carry the player's current nonce
+  const moveNonce = async (player: PublicKey) =>
+    (await program.account.player.fetch(player)).moveNonce;
+
+  // Session key scope bit for movePlayer
+  const SCOPE_MOVE_PLAYER = 1;
+
+  const { board: boardPda, player: mainPlayerPda } = boardPdas(boardId);
+
+  const boardConfig = {
+    width: 100,
+    height: 100,
+    spawnX: 10,
+    spawnY: 10,
+    maxStep: 10,
+    maxPlayers: 16,
+    movementMode: { free: {} },
+    minCommitFrequencyMs: 1_000,
+    maxCommitFrequencyMs: 60_000,
+    maxDelegationTime: new anchor.BN(24 * 60 * 60),
+    moveCooldownTicks: 0,
+  };
 
   // Local ER validator for testing
   const LOCAL_ER_VALIDATOR = new PublicKey("mAGicPQYBMvcYveUZA5F5UNNwyHvfYh5xkLS2Fr1mev");
+  const erProgram = new Program<Test2>(
+    program.idl,
+    new anchor.AnchorProvider(
+      new anchor.web3.Connection(
+        process.env.EPHEMERAL_PROVIDER_ENDPOINT || "http://localhost:7799",
+        "confirmed"
+      ),
+      anchor.AnchorProvider.env().wallet
+    )
+  );
+
+  // Matches start by handing the board's occupancy to the ER
+  const delegateOccupancy = (board: PublicKey) =>
+    program.methods
+      .delegateOccupancy()
+      .accounts({
+        payer: provider.publicKey,
+        authority: provider.publicKey,
+        board,
+      })
+      .remainingAccounts([
+        { pubkey: LOCAL_ER_VALIDATOR, isSigner: false, isWritable: false }
+      ])
+      .rpc();
 
   it("Initializes the board", async () => {
     const tx = await program.methods
-      .initialize()
+      .initialize(boardId, boardConfig)
       .rpc();
     console.log("Board initialized:", tx);
 
     const board = await program.account.board.fetch(boardPda);
     expect(board.authority.toString()).to.equal(provider.publicKey.toString());
+    expect(board.config.width).to.equal(100);
+    expect(board.config.maxStep).to.equal(10);
+    expect(board.playerCount).to.equal(0);
+  });
+
+  it("Board state chunks track cells and their occupants", async () => {
+    const stateBoardId = boardId.addn(10);
+    const { board: stateBoardPda, player: statePlayerPda } = boardPdas(stateBoardId);
+    const chunkPda = (chunk: number) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("board_state"),
+          stateBoardPda.toBuffer(),
+          new anchor.BN(chunk).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      )[0];
+    // Rows of 100 cells, 25 rows to a chunk
+    const cell = async (x: number, y: number) =>
+      (await program.account.boardState.fetch(chunkPda(Math.floor(y / 25)))).cells[
+        (y % 25) * 100 + x
+      ];
+
+    await program.methods.initialize(stateBoardId, boardConfig).rpc();
+    const BOARD_STATE_CHUNKS = 4;
+    for (let chunk = 0; chunk < BOARD_STATE_CHUNKS; chunk++) {
+      await program.methods
+        .initBoardState(chunk)
+        .accounts({ board: stateBoardPda })
+        .rpc();
+    }
+
+    const boardState = await program.account.boardState.fetch(chunkPda(BOARD_STATE_CHUNKS - 1));
+    expect(boardState.board.toString()).to.equal(stateBoardPda.toString());
+    expect(boardState.chunk).to.equal(BOARD_STATE_CHUNKS - 1);
+
+    try {
+      await program.methods
+        .initBoardState(BOARD_STATE_CHUNKS)
+        .accounts({ board: stateBoardPda })
+        .rpc();
+      expect.fail("chunk past the end of the board should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidBoardStateChunk");
+    }
+
+    // Placing or moving players takes every open chunk
+    let chunks = [0, 1, 2, 3].map((chunk) => ({
+      pubkey: chunkPda(chunk),
+      isSigner: false,
+      isWritable: true,
+    }));
+    try {
+      await program.methods
+        .joinGame()
+        .accounts({ board: stateBoardPda })
+        .remainingAccounts(chunks.slice(1))
+        .rpc();
+      expect.fail("join without every open chunk should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BoardStateMissing");
+    }
+    await program.methods
+      .joinGame()
+      .accounts({ board: stateBoardPda })
+      .remainingAccounts(chunks)
+      .rpc();
+    const player = await program.account.player.fetch(statePlayerPda);
+    expect(player.index).to.equal(1);
+    expect((await cell(10, 10)).occupant).to.equal(1);
+
+    await program.methods
+      .movePlayer(1, 0, player.moveNonce)
+      .accounts({ player: statePlayerPda, board: stateBoardPda })
+      .remainingAccounts(chunks)
+      .rpc();
+    expect((await cell(10, 10)).occupant).to.equal(0);
+    expect((await cell(11, 10)).occupant).to.equal(1);
+
+    // Only the authority shapes the world, and occupants are left to the game
+    await program.methods
+      .setCell(0, 11, 10, 2, 7)
+      .accounts({ board: stateBoardPda })
+      .rpc();
+    expect(await cell(11, 10)).to.deep.equal({ terrain: 2, item: 7, occupant: 1 });
+
+    const stranger = Keypair.generate();
+    try {
+      await program.methods
+        .setCell(0, 11, 10, 0, 0)
+        .accounts({ board: stateBoardPda, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      expect.fail("stranger should not set cells");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnauthorizedAuthority");
+    }
+    try {
+      await program.methods
+        .setCell(1, 11, 10, 0, 0)
+        .accounts({ board: stateBoardPda })
+        .rpc();
+      expect.fail("cell outside the chunk should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidBoardStateChunk");
+    }
+
+    // A new chunk couldn't know who already stands on its cells
+    await program.methods
+      .closeBoardState(3)
+      .accounts({ board: stateBoardPda })
+      .rpc();
+    chunks = chunks.slice(0, 3);
+    try {
+      await program.methods
+        .initBoardState(3)
+        .accounts({ board: stateBoardPda })
+        .rpc();
+      expect.fail("chunk opened with players on the board should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BoardNotEmpty");
+    }
+
+    await program.methods
+      .leaveGame()
+      .accounts({ board: stateBoardPda })
+      .remainingAccounts(chunks)
+      .rpc();
+    expect((await cell(11, 10)).occupant).to.equal(0);
   });
 
   it("Player joins the game at position (10, 10)", async () => {
     const tx = await program.methods
       .joinGame()
+      .accounts({ board: boardPda })
       .rpc();
     console.log("Player joined:", tx);
 
-    const player = await program.account.player.fetch(playerPda);
+    const player = await program.account.player.fetch(mainPlayerPda);
     expect(player.x).to.equal(10);
     expect(player.y).to.equal(10);
     expect(player.authority.toString()).to.equal(provider.publicKey.toString());
+    expect(player.board.toString()).to.equal(boardPda.toString());
+    expect(player.delegation.delegated).to.be.false;
+
+    const board = await program.account.board.fetch(boardPda);
+    expect(board.playerCount).to.equal(1);
   });
 
   it("Player moves on the grid", async () => {
     // Move right and up
     await program.methods
-      .movePlayer(5, -3)
+      .movePlayer(5, -3, await moveNonce(mainPlayerPda))
+      .accounts({ player: mainPlayerPda, board: boardPda })
       .rpc();
 
-    let player = await program.account.player.fetch(playerPda);
+    let player = await program.account.player.fetch(mainPlayerPda);
     expect(player.x).to.equal(15); // 10 + 5
     expect(player.y).to.equal(7);  // 10 - 3
 
     // Move left and down
     await program.methods
-      .movePlayer(-2, 4)
+      .movePlayer(-2, 4, await moveNonce(mainPlayerPda))
+      .accounts({ player: mainPlayerPda, board: boardPda })
       .rpc();
 
-    player = await program.account.player.fetch(playerPda);
+    player = await program.account.player.fetch(mainPlayerPda);
     expect(player.x).to.equal(13); // 15 - 2
     expect(player.y).to.equal(11); // 7 + 4
   });
 
+  it("Moves are reported as typed events", async () => {
+    const events = [];
+    const listener = program.addEventListener("playerMoved", (event) => events.push(event));
+
+    try {
+      await program.methods
+        .movePlayer(1, 0, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: boardPda })
+        .rpc({ commitment: "confirmed" });
+      await program.methods
+        .movePlayer(-1, 0, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: boardPda })
+        .rpc({ commitment: "confirmed" });
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    } finally {
+      await program.removeEventListener(listener);
+    }
+
+    expect(events).to.have.lengthOf(2);
+    expect(events[0].player.toString()).to.equal(mainPlayerPda.toString());
+    expect([events[0].fromX, events[0].x]).to.deep.equal([13, 14]);
+    expect([events[1].fromX, events[1].x]).to.deep.equal([14, 13]);
+  });
+
+  it("Moves must quote the current nonce", async () => {
+    const nonce = await moveNonce(mainPlayerPda);
+
+    await program.methods
+      .movePlayer(1, 0, nonce)
+      .accounts({ player: mainPlayerPda, board: boardPda })
+      .rpc();
+
+    // A move quoting an already used nonce is rejected
+    try {
+      await program.methods
+        .movePlayer(0, 1, nonce)
+        .accounts({ player: mainPlayerPda, board: boardPda })
+        .rpc();
+      expect.fail("stale nonce should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MoveNonceMismatch");
+    }
+
+    await program.methods
+      .movePlayer(-1, 0, nonce.addn(1))
+      .accounts({ player: mainPlayerPda, board: boardPda })
+      .rpc();
+    expect((await moveNonce(mainPlayerPda)).toNumber()).to.equal(nonce.toNumber() + 2);
+  });
+
+  it("Player follows a path in one move", async () => {
+    const before = await program.account.player.fetch(mainPlayerPda);
+    const step = (xDirection: number, yDirection: number) => ({ xDirection, yDirection });
+
+    await program.methods
+      .movePath([step(1, 0), step(1, 0), step(0, 1)], before.moveNonce)
+      .accounts({ player: mainPlayerPda, board: boardPda })
+      .rpc();
+
+    let player = await program.account.player.fetch(mainPlayerPda);
+    expect([player.x, player.y]).to.deep.equal([before.x + 2, before.y + 1]);
+    expect(player.moveNonce.toNumber()).to.equal(before.moveNonce.toNumber() + 1);
+
+    // One invalid step rejects the whole path
+    try {
+      await program.methods
+        .movePath([step(-1, 0), step(0, -100)], player.moveNonce)
+        .accounts({ player: mainPlayerPda, board: boardPda })
+        .rpc();
+      expect.fail("path with an oversized step should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MoveTooLarge");
+    }
+
+    await program.methods
+      .movePath([step(-1, 0), step(-1, 0), step(0, -1)], player.moveNonce)
+      .accounts({ player: mainPlayerPda, board: boardPda })
+      .rpc();
+    player = await program.account.player.fetch(mainPlayerPda);
+    expect([player.x, player.y]).to.deep.equal([before.x, before.y]);
+  });
+
   it("Player cannot move outside grid boundaries", async () => {
-    // Try to move far beyond grid bounds
     await program.methods
-      .movePlayer(-50, -50)
+      .movePlayer(-10, -10, await moveNonce(mainPlayerPda))
+      .accounts({ player: mainPlayerPda, board: boardPda })
       .rpc();
 
-    let player = await program.account.player.fetch(playerPda);
-    expect(player.x).to.equal(0); // Clamped to 0
-    expect(player.y).to.equal(0); // Clamped to 0
+    let player = await program.account.player.fetch(mainPlayerPda);
+    expect(player.x).to.equal(3); // 13 - 10
+    expect(player.y).to.equal(1); // 11 - 10
 
-    // Try to move to maximum bounds
     await program.methods
-      .movePlayer(127, 127)
+      .movePlayer(-3, -1, await moveNonce(mainPlayerPda))
+      .accounts({ player: mainPlayerPda, board: boardPda })
       .rpc();
 
-    player = await program.account.player.fetch(playerPda);
-    expect(player.x).to.equal(99); // Clamped to 99
-    expect(player.y).to.equal(99); // Clamped to 99
+    // Stepping past the top-left corner is rejected rather than clamped
+    try {
+      await program.methods
+        .movePlayer(-1, -1, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: boardPda })
+        .rpc();
+      expect.fail("move off the board should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("OutOfBounds");
+    }
+
+    player = await program.account.player.fetch(mainPlayerPda);
+    expect(player.x).to.equal(0);
+    expect(player.y).to.equal(0);
+  });
+
+  it("Player cannot move further than the maximum step", async () => {
+    try {
+      await program.methods
+        .movePlayer(127, 127, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: boardPda })
+        .rpc();
+      expect.fail("oversized move should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("MoveTooLarge");
+    }
+
+    const player = await program.account.player.fetch(mainPlayerPda);
+    expect(player.x).to.equal(0);
+    expect(player.y).to.equal(0);
+  });
+
+  it("Runs a separate board with its own rules", async () => {
+    const otherBoardId = boardId.addn(1);
+    const { board: otherBoardPda, player: otherPlayerPda } = boardPdas(otherBoardId);
+
+    await program.methods
+      .initialize(otherBoardId, {
+        ...boardConfig,
+        width: 20,
+        height: 20,
+        spawnX: 0,
+        spawnY: 0,
+        maxStep: 1,
+        movementMode: { fourWay: {} },
+      })
+      .rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: otherBoardPda })
+      .rpc();
+
+    try {
+      await program.methods
+        .movePlayer(1, 1, await moveNonce(otherPlayerPda))
+        .accounts({ player: otherPlayerPda, board: otherBoardPda })
+        .rpc();
+      expect.fail("diagonal move should be rejected on a four-way board");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidDirection");
+    }
+
+    // The player PDA is seeded by its own board, so it cannot be moved on this one
+    let rejected = false;
+    try {
+      await program.methods
+        .movePlayer(1, 0, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: otherBoardPda })
+        .rpc();
+    } catch (error) {
+      rejected = true;
+    }
+    expect(rejected).to.be.true;
+
+    const player = await program.account.player.fetch(otherPlayerPda);
+    expect(player.x).to.equal(0);
+    expect(player.y).to.equal(0);
+  });
+
+  it("Board authority administers the board", async () => {
+    const adminBoardId = boardId.addn(2);
+    const { board: adminBoardPda, player: adminPlayerPda } = boardPdas(adminBoardId);
+    const stranger = Keypair.generate();
+
+    await program.methods.initialize(adminBoardId, boardConfig).rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: adminBoardPda })
+      .rpc();
+
+    try {
+      await program.methods
+        .setPaused(true)
+        .accounts({ board: adminBoardPda, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      expect.fail("stranger should not administer the board");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnauthorizedAuthority");
+    }
+
+    const configEvents = [];
+    const listener = program.addEventListener("boardConfigUpdated", (event) =>
+      configEvents.push(event)
+    );
+    try {
+      await program.methods
+        .updateBoardConfig({ ...boardConfig, maxStep: 3 })
+        .accounts({ board: adminBoardPda })
+        .rpc({ commitment: "confirmed" });
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    } finally {
+      await program.removeEventListener(listener);
+    }
+    expect(configEvents).to.have.lengthOf(1);
+    expect(configEvents[0].board.toString()).to.equal(adminBoardPda.toString());
+    expect(configEvents[0].config.maxStep).to.equal(3);
+
+    let board = await program.account.board.fetch(adminBoardPda);
+    expect(board.config.maxStep).to.equal(3);
+
+    await program.methods
+      .kickPlayer()
+      .accounts({
+        board: adminBoardPda,
+        player: adminPlayerPda,
+        playerAuthority: provider.publicKey,
+      })
+      .rpc();
+    board = await program.account.board.fetch(adminBoardPda);
+    expect(board.playerCount).to.equal(0);
+    expect(await provider.connection.getAccountInfo(adminPlayerPda)).to.be.null;
+
+    // Open board state chunks would be stranded without their board
+    await program.methods
+      .initBoardState(0)
+      .accounts({ board: adminBoardPda })
+      .rpc();
+    try {
+      await program.methods
+        .closeBoard()
+        .accounts({ board: adminBoardPda })
+        .rpc();
+      expect.fail("board with open state chunks should not close");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BoardStateOpen");
+    }
+    await program.methods
+      .closeBoardState(0)
+      .accounts({ board: adminBoardPda })
+      .rpc();
+
+    await program.methods
+      .closeBoard()
+      .accounts({ board: adminBoardPda })
+      .rpc();
+    expect(await provider.connection.getAccountInfo(adminBoardPda)).to.be.null;
+  });
+
+  it("Player leaves and rejoins a board", async () => {
+    const lobbyBoardId = boardId.addn(3);
+    const { board: lobbyBoardPda, player: lobbyPlayerPda } = boardPdas(lobbyBoardId);
+
+    await program.methods.initialize(lobbyBoardId, boardConfig).rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: lobbyBoardPda })
+      .rpc();
+
+    await program.methods
+      .leaveGame()
+      .accounts({ player: lobbyPlayerPda, board: lobbyBoardPda })
+      .rpc();
+    expect(await provider.connection.getAccountInfo(lobbyPlayerPda)).to.be.null;
+    let board = await program.account.board.fetch(lobbyBoardPda);
+    expect(board.playerCount).to.equal(0);
+
+    await program.methods
+      .joinGame()
+      .accounts({ board: lobbyBoardPda })
+      .rpc();
+    board = await program.account.board.fetch(lobbyBoardPda);
+    expect(board.playerCount).to.equal(1);
+  });
+
+  it("Players cannot share a cell", async () => {
+    const arenaBoardId = boardId.addn(4);
+    const { board: arenaBoardPda } = boardPdas(arenaBoardId);
+    const rival = Keypair.generate();
+    const rivalPlayerPda = playerPda(arenaBoardPda, rival.publicKey);
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(rival.publicKey, 1_000_000_000)
+    );
+
+    await program.methods
+      .initialize(arenaBoardId, { ...boardConfig, spawnX: 0, spawnY: 0 })
+      .rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: arenaBoardPda })
+      .rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: arenaBoardPda, authority: rival.publicKey })
+      .signers([rival])
+      .rpc();
+
+    // The spawn point is taken, so the rival spawns on the next free cell
+    const rivalPlayer = await program.account.player.fetch(rivalPlayerPda);
+    expect(rivalPlayer.x).to.equal(1);
+    expect(rivalPlayer.y).to.equal(0);
+
+    try {
+      await program.methods
+        .movePlayer(-1, 0, await moveNonce(rivalPlayerPda))
+        .accounts({ player: rivalPlayerPda, board: arenaBoardPda, signer: rival.publicKey })
+        .signers([rival])
+        .rpc();
+      expect.fail("move onto an occupied cell should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("CellOccupied");
+    }
+  });
+
+  it("Ticks count down move cooldowns", async () => {
+    const tickBoardId = boardId.addn(5);
+    const { board: tickBoardPda, player: tickPlayerPda } = boardPdas(tickBoardId);
+
+    await program.methods
+      .initialize(tickBoardId, { ...boardConfig, moveCooldownTicks: 2 })
+      .rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: tickBoardPda })
+      .rpc();
+
+    await program.methods
+      .movePlayer(1, 0, await moveNonce(tickPlayerPda))
+      .accounts({ player: tickPlayerPda, board: tickBoardPda })
+      .rpc();
+
+    const tick = () =>
+      program.methods
+        .tick()
+        .accounts({ board: tickBoardPda })
+        .remainingAccounts([{ pubkey: tickPlayerPda, isSigner: false, isWritable: true }])
+        .rpc();
+
+    for (let remaining = 2; remaining > 0; remaining--) {
+      try {
+        await program.methods
+          .movePlayer(1, 0, await moveNonce(tickPlayerPda))
+          .accounts({ player: tickPlayerPda, board: tickBoardPda })
+          .rpc();
+        expect.fail("move should be rejected while on cooldown");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("MoveOnCooldown");
+      }
+      await tick();
+    }
+
+    await program.methods
+      .movePlayer(1, 0, await moveNonce(tickPlayerPda))
+      .accounts({ player: tickPlayerPda, board: tickBoardPda })
+      .rpc();
+
+    const board = await program.account.board.fetch(tickBoardPda);
+    expect(board.tick.toNumber()).to.equal(2);
+  });
+
+  it("Player drifts along its velocity", async () => {
+    const driftBoardId = boardId.addn(6);
+    const { board: driftBoardPda, player: driftPlayerPda } = boardPdas(driftBoardId);
+
+    await program.methods.initialize(driftBoardId, boardConfig).rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: driftBoardPda })
+      .rpc();
+
+    await program.methods
+      .setVelocity(1, 0, await moveNonce(driftPlayerPda))
+      .accounts({ player: driftPlayerPda, board: driftBoardPda })
+      .rpc();
+    const start = await program.account.player.fetch(driftPlayerPda);
+    expect([start.velocityX, start.velocityY]).to.deep.equal([1, 0]);
+
+    // Wait for a few velocity steps' worth of slots, then let a tick integrate them
+    const startSlot = await provider.connection.getSlot();
+    while ((await provider.connection.getSlot()) < startSlot + 30) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+    const tick = () =>
+      program.methods
+        .tick()
+        .accounts({ board: driftBoardPda })
+        .remainingAccounts([{ pubkey: driftPlayerPda, isSigner: false, isWritable: true }])
+        .rpc();
+    await tick();
+
+    const player = await program.account.player.fetch(driftPlayerPda);
+    expect(player.x).to.be.at.least(start.x + 3);
+    expect(player.y).to.equal(start.y);
+
+    // Nothing drifts while the board is paused, nor catches up afterwards
+    await program.methods
+      .setPaused(true)
+      .accounts({ board: driftBoardPda })
+      .rpc();
+    const pausedSlot = await provider.connection.getSlot();
+    try {
+      await tick();
+      expect.fail("tick should be rejected while paused");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("GamePaused");
+    }
+    while ((await provider.connection.getSlot()) < pausedSlot + 30) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+    await program.methods
+      .setPaused(false)
+      .accounts({ board: driftBoardPda })
+      .rpc();
+    await tick();
+
+    const resumed = await program.account.player.fetch(driftPlayerPda);
+    expect(resumed.x).to.be.at.most(player.x + 1);
+  });
+
+  it("Paused board rejects gameplay", async () => {
+    await program.methods
+      .setPaused(true)
+      .accounts({ board: boardPda })
+      .rpc();
+
+    try {
+      await program.methods
+        .movePlayer(1, 0, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: boardPda })
+        .rpc();
+      expect.fail("move should be rejected while paused");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("GamePaused");
+    }
+
+    await program.methods
+      .setPaused(false)
+      .accounts({ board: boardPda })
+      .rpc();
+  });
+
+  it("Rejects a board that was never initialized", async () => {
+    const { board: missingBoardPda } = boardPdas(boardId.addn(99));
+
+    try {
+      await program.methods
+        .joinGame()
+        .accounts({ board: missingBoardPda })
+        .rpc();
+      expect.fail("joining a missing board should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BoardNotInitialized");
+    }
+
+    try {
+      await program.methods
+        .movePlayer(1, 0, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: missingBoardPda })
+        .rpc();
+      expect.fail("moving on a missing board should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BoardNotInitialized");
+    }
+  });
+
+  it("Session key moves within its scope and use limit", async () => {
+    const sessionKey = Keypair.generate();
+
+    await program.methods
+      .registerSessionKey("desktop", new anchor.BN(60 * 60), SCOPE_MOVE_PLAYER, 1)
+      .accounts({ player: mainPlayerPda, board: boardPda, sessionKey: sessionKey.publicKey })
+      .signers([sessionKey])
+      .rpc();
+
+    await program.methods
+      .movePlayer(1, 0, await moveNonce(mainPlayerPda))
+      .accounts({ player: mainPlayerPda, board: boardPda, signer: sessionKey.publicKey })
+      .signers([sessionKey])
+      .rpc();
+
+    let player = await program.account.player.fetch(mainPlayerPda);
+    expect(player.x).to.equal(1);
+    expect(player.sessions[0].label).to.equal("desktop");
+    expect(player.sessions[0].usesRemaining).to.equal(0);
+
+    try {
+      await program.methods
+        .movePlayer(1, 0, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: boardPda, signer: sessionKey.publicKey })
+        .signers([sessionKey])
+        .rpc();
+      expect.fail("exhausted session key should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SessionExhausted");
+    }
+
+    // An exhausted session can be cleaned up by anyone
+    const stranger = Keypair.generate();
+    await program.methods
+      .revokeSessionKey(sessionKey.publicKey)
+      .accounts({ player: mainPlayerPda, signer: stranger.publicKey })
+      .signers([stranger])
+      .rpc();
+    player = await program.account.player.fetch(mainPlayerPda);
+    expect(player.sessions).to.be.empty;
+  });
+
+  it("Player keeps several session keys active at once", async () => {
+    const phone = Keypair.generate();
+    const desktop = Keypair.generate();
+
+    for (const [label, sessionKey] of [["phone", phone], ["desktop", desktop]] as const) {
+      await program.methods
+        .registerSessionKey(label, new anchor.BN(60 * 60), SCOPE_MOVE_PLAYER, 10)
+        .accounts({ player: mainPlayerPda, board: boardPda, sessionKey: sessionKey.publicKey })
+        .signers([sessionKey])
+        .rpc();
+    }
+
+    for (const sessionKey of [phone, desktop]) {
+      await program.methods
+        .movePlayer(0, 1, await moveNonce(mainPlayerPda))
+        .accounts({ player: mainPlayerPda, board: boardPda, signer: sessionKey.publicKey })
+        .signers([sessionKey])
+        .rpc();
+    }
+
+    let player = await program.account.player.fetch(mainPlayerPda);
+    expect(player.sessions).to.have.lengthOf(2);
+    expect(player.y).to.equal(2);
+
+    await program.methods
+      .revokeAllSessions()
+      .accounts({ player: mainPlayerPda })
+      .rpc();
+    player = await program.account.player.fetch(mainPlayerPda);
+    expect(player.sessions).to.be.empty;
+  });
+
+  it("Session key is topped up and returns its funds", async () => {
+    const sessionKey = Keypair.generate();
+    const topUp = 5_000_000;
+
+    await program.methods
+      .registerSessionKey("browser", new anchor.BN(60 * 60), SCOPE_MOVE_PLAYER, 10)
+      .accounts({ player: mainPlayerPda, board: boardPda, sessionKey: sessionKey.publicKey })
+      .signers([sessionKey])
+      .rpc();
+
+    try {
+      await program.methods
+        .fundSessionKey(new anchor.BN(20_000_000))
+        .accounts({ sessionKey: sessionKey.publicKey })
+        .rpc();
+      expect.fail("oversized top-up should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("SessionTopUpTooLarge");
+    }
+
+    await program.methods
+      .fundSessionKey(new anchor.BN(topUp))
+      .accounts({ sessionKey: sessionKey.publicKey })
+      .rpc();
+    expect(await provider.connection.getBalance(sessionKey.publicKey)).to.equal(topUp);
+
+    // The session key pays for its own reclaim and hands back the rest
+    const tx = await program.methods
+      .reclaimSessionFunds()
+      .accounts({ player: mainPlayerPda, sessionKey: sessionKey.publicKey })
+      .transaction();
+    tx.feePayer = sessionKey.publicKey;
+    await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [sessionKey]);
+
+    expect(await provider.connection.getBalance(sessionKey.publicKey)).to.equal(0);
+    const player = await program.account.player.fetch(mainPlayerPda);
+    expect(player.sessions).to.be.empty;
+  });
+
+  it("Stranger cannot commit or undelegate someone else's player", async () => {
+    const stranger = Keypair.generate();
+
+    for (const method of ["commitPlayer", "undelegatePlayer"]) {
+      try {
+        await program.methods[method]()
+          .accounts({ payer: stranger.publicKey, board: boardPda, player: mainPlayerPda })
+          .signers([stranger])
+          .rpc();
+        expect.fail(`stranger should not be able to ${method}`);
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("UnauthorizedSigner");
+      }
+    }
+  });
+
+  it("Batch commits are checked before anything is committed", async () => {
+    const stranger = Keypair.generate();
+    const { board: otherBoardPda, player: otherPlayerPda } = boardPdas(boardId.addn(1));
+    const asRemaining = (pubkey: PublicKey) => [{ pubkey, isSigner: false, isWritable: true }];
+
+    try {
+      await program.methods
+        .commitPlayers()
+        .accounts({ board: boardPda })
+        .rpc();
+      expect.fail("empty batch should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NoPlayersToCommit");
+    }
+
+    try {
+      await program.methods
+        .commitPlayers()
+        .accounts({ board: boardPda, authority: stranger.publicKey })
+        .remainingAccounts(asRemaining(mainPlayerPda))
+        .signers([stranger])
+        .rpc();
+      expect.fail("stranger should not batch commit");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnauthorizedAuthority");
+    }
+
+    try {
+      await program.methods
+        .commitPlayers()
+        .accounts({ board: boardPda })
+        .remainingAccounts(asRemaining(otherPlayerPda))
+        .rpc();
+      expect.fail("player from another board should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("PlayerNotOnBoard");
+    }
+  });
+
+  it("Only the board authority can delegate the board", async () => {
+    const stranger = Keypair.generate();
+
+    try {
+      await program.methods
+        .delegateBoard(boardId)
+        .accounts({
+          payer: provider.publicKey,
+          authority: stranger.publicKey,
+          board: boardPda,
+        })
+        .signers([stranger])
+        .rpc();
+      expect.fail("stranger should not delegate the board");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnauthorizedAuthority");
+    }
+  });
+
+  it("Delegation must respect the board's commit frequency bounds", async () => {
+    try {
+      await program.methods
+        .delegatePlayer(100, new anchor.BN(60 * 60))
+        .accounts({
+          payer: provider.publicKey,
+          authority: provider.publicKey,
+          board: boardPda,
+          pda: mainPlayerPda,
+        })
+        .rpc();
+      expect.fail("commit frequency below the board minimum should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidDelegationConfig");
+    }
+  });
+
+  it("Delegation is limited to allow-listed validators", async () => {
+    const rogueValidator = Keypair.generate().publicKey;
+
+    try {
+      await program.methods
+        .delegatePlayer(30_000, new anchor.BN(60 * 60))
+        .accounts({
+          payer: provider.publicKey,
+          authority: provider.publicKey,
+          board: boardPda,
+          pda: mainPlayerPda,
+        })
+        .remainingAccounts([
+          { pubkey: rogueValidator, isSigner: false, isWritable: false }
+        ])
+        .rpc();
+      expect.fail("delegation to an unapproved validator should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ValidatorNotAllowed");
+    }
+
+    // The board and its shared accounts are held to the same allow-list
+    const rogue = [{ pubkey: rogueValidator, isSigner: false, isWritable: false }];
+    const boardDelegations = [
+      program.methods.delegateBoard(boardId).accounts({
+        payer: provider.publicKey,
+        authority: provider.publicKey,
+        board: boardPda,
+      }),
+      program.methods.delegateOccupancy().accounts({
+        payer: provider.publicKey,
+        authority: provider.publicKey,
+        board: boardPda,
+      }),
+      program.methods.delegateBoardState(0).accounts({
+        payer: provider.publicKey,
+        authority: provider.publicKey,
+        board: boardPda,
+      }),
+    ];
+    for (const delegation of boardDelegations) {
+      try {
+        await delegation.remainingAccounts(rogue).rpc();
+        expect.fail("board delegation to an unapproved validator should be rejected");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("ValidatorNotAllowed");
+      }
+    }
+
+    await program.methods
+      .addValidator(LOCAL_ER_VALIDATOR)
+      .accounts({ board: boardPda })
+      .rpc();
+    const board = await program.account.board.fetch(boardPda);
+    expect(board.allowedValidators.map((v) => v.toString())).to.include(
+      LOCAL_ER_VALIDATOR.toString()
+    );
+  });
+
+  it("Players wait for the occupancy to enter the ER", async () => {
+    try {
+      await program.methods
+        .delegatePlayer(30_000, new anchor.BN(60 * 60))
+        .accounts({
+          payer: provider.publicKey,
+          authority: provider.publicKey,
+          board: boardPda,
+          pda: mainPlayerPda,
+        })
+        .remainingAccounts([
+          { pubkey: LOCAL_ER_VALIDATOR, isSigner: false, isWritable: false }
+        ])
+        .rpc();
+      expect.fail("delegation before the occupancy should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("OccupancyNotDelegated");
+    }
   });
 
   it("Delegates player to Ephemeral Rollup", async () => {
@@ -91,12 +1018,14 @@ describe("test-2", () => {
     // For full testing, run with: magicblock-validator
 
     try {
+      await delegateOccupancy(boardPda);
       await program.methods
-        .delegatePlayer()
+        .delegatePlayer(30_000, new anchor.BN(60 * 60))
         .accounts({
           payer: provider.publicKey,
           authority: provider.publicKey,
-          pda: playerPda,
+          board: boardPda,
+          pda: mainPlayerPda,
         })
         .remainingAccounts([
           { pubkey: LOCAL_ER_VALIDATOR, isSigner: false, isWritable: false }
@@ -106,6 +1035,201 @@ describe("test-2", () => {
       console.log("✓ Player delegated to ER (requires running ER validator)");
     } catch (error) {
       console.log("⚠ Delegation skipped (ER validator not running):", error.message);
+      return;
+    }
+
+    // The delegation program owns the player now, so decode its snapshot by hand
+    const info = await provider.connection.getAccountInfo(mainPlayerPda);
+    const player = program.coder.accounts.decode("player", info.data);
+    const now = Math.floor(Date.now() / 1000);
+    expect(player.delegation.delegated).to.be.true;
+    expect(player.delegation.validator.toString()).to.equal(LOCAL_ER_VALIDATOR.toString());
+    expect(player.delegation.delegatedAt.toNumber()).to.be.greaterThan(0);
+    expect(player.delegation.expiresAt.toNumber()).to.be.within(now + 60 * 60 - 120, now + 60 * 60 + 120);
+  });
+
+  it("Drifting players stop before entering and leaving the ER", async () => {
+    const driftBoardId = boardId.addn(7);
+    const { board: driftBoardPda, player: driftPlayerPda } = boardPdas(driftBoardId);
+
+    await program.methods.initialize(driftBoardId, boardConfig).rpc();
+    await program.methods
+      .addValidator(LOCAL_ER_VALIDATOR)
+      .accounts({ board: driftBoardPda })
+      .rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: driftBoardPda })
+      .rpc();
+    await program.methods
+      .setVelocity(1, 0, await moveNonce(driftPlayerPda))
+      .accounts({ player: driftPlayerPda, board: driftBoardPda })
+      .rpc();
+
+    const delegate = () =>
+      program.methods
+        .delegatePlayer(30_000, new anchor.BN(60 * 60))
+        .accounts({
+          payer: provider.publicKey,
+          authority: provider.publicKey,
+          board: driftBoardPda,
+          pda: driftPlayerPda,
+        })
+        .remainingAccounts([
+          { pubkey: LOCAL_ER_VALIDATOR, isSigner: false, isWritable: false }
+        ])
+        .rpc();
+
+    // The drift can't be settled once the occupancy is in the ER
+    try {
+      await delegate();
+      expect.fail("delegating a drifting player should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("PlayerMoving");
+    }
+    await program.methods
+      .setVelocity(0, 0, await moveNonce(driftPlayerPda))
+      .accounts({ player: driftPlayerPda, board: driftBoardPda })
+      .rpc();
+
+    try {
+      await delegateOccupancy(driftBoardPda);
+      await delegate();
+    } catch (error) {
+      console.log("⚠ Delegation skipped (ER validator not running):", error.message);
+      return;
+    }
+
+    // Undelegating settles the drift made in the ER before stopping
+    const start = await erProgram.account.player.fetch(driftPlayerPda);
+    await erProgram.methods
+      .setVelocity(1, 0, start.moveNonce)
+      .accounts({ player: driftPlayerPda, board: driftBoardPda })
+      .rpc();
+    const startSlot = await erProgram.provider.connection.getSlot();
+    while ((await erProgram.provider.connection.getSlot()) < startSlot + 30) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+    await erProgram.methods
+      .undelegatePlayer()
+      .accounts({ payer: provider.publicKey, board: driftBoardPda, player: driftPlayerPda })
+      .rpc();
+
+    let info = await provider.connection.getAccountInfo(driftPlayerPda);
+    for (let attempt = 0; attempt < 30 && !info.owner.equals(program.programId); attempt++) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+      info = await provider.connection.getAccountInfo(driftPlayerPda);
+    }
+    const player = program.coder.accounts.decode("player", info.data);
+    expect(player.x).to.be.at.least(start.x + 3);
+    expect([player.velocityX, player.velocityY]).to.deep.equal([0, 0]);
+  });
+
+  it("Players join before the board is delegated", async () => {
+    const liveBoardId = boardId.addn(8);
+    const { board: liveBoardPda, player: livePlayerPda } = boardPdas(liveBoardId);
+    const latecomer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(latecomer.publicKey, 1_000_000_000)
+    );
+
+    await program.methods.initialize(liveBoardId, boardConfig).rpc();
+    await program.methods
+      .addValidator(LOCAL_ER_VALIDATOR)
+      .accounts({ board: liveBoardPda })
+      .rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: liveBoardPda })
+      .rpc();
+
+    try {
+      await delegateOccupancy(liveBoardPda);
+      await program.methods
+        .delegateBoard(liveBoardId)
+        .accounts({
+          payer: provider.publicKey,
+          authority: provider.publicKey,
+          board: liveBoardPda,
+        })
+        .remainingAccounts([
+          { pubkey: LOCAL_ER_VALIDATOR, isSigner: false, isWritable: false }
+        ])
+        .rpc();
+    } catch (error) {
+      console.log("⚠ Delegation skipped (ER validator not running):", error.message);
+      return;
     }
+
+    // The lobby is closed while the board is in the ER
+    try {
+      await program.methods
+        .joinGame()
+        .accounts({ board: liveBoardPda, authority: latecomer.publicKey })
+        .signers([latecomer])
+        .rpc();
+      expect.fail("joining a delegated board should be rejected");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("AccountOwnedByWrongProgram");
+    }
+
+    // Players who already joined can still be delegated against it
+    await program.methods
+      .delegatePlayer(30_000, new anchor.BN(60 * 60))
+      .accounts({
+        payer: provider.publicKey,
+        authority: provider.publicKey,
+        board: liveBoardPda,
+        pda: livePlayerPda,
+      })
+      .remainingAccounts([
+        { pubkey: LOCAL_ER_VALIDATOR, isSigner: false, isWritable: false }
+      ])
+      .rpc();
+  });
+
+  it("Delegated player moves in the Ephemeral Rollup", async () => {
+    const erBoardId = boardId.addn(9);
+    const { board: erBoardPda, player: erPlayerPda } = boardPdas(erBoardId);
+
+    await program.methods.initialize(erBoardId, boardConfig).rpc();
+    await program.methods
+      .addValidator(LOCAL_ER_VALIDATOR)
+      .accounts({ board: erBoardPda })
+      .rpc();
+    await program.methods
+      .joinGame()
+      .accounts({ board: erBoardPda })
+      .rpc();
+    const start = await program.account.player.fetch(erPlayerPda);
+
+    try {
+      await delegateOccupancy(erBoardPda);
+      await program.methods
+        .delegatePlayer(30_000, new anchor.BN(60 * 60))
+        .accounts({
+          payer: provider.publicKey,
+          authority: provider.publicKey,
+          board: erBoardPda,
+          pda: erPlayerPda,
+        })
+        .remainingAccounts([
+          { pubkey: LOCAL_ER_VALIDATOR, isSigner: false, isWritable: false }
+        ])
+        .rpc();
+    } catch (error) {
+      console.log("⚠ Delegation skipped (ER validator not running):", error.message);
+      return;
+    }
+
+    // Moves now go to the ER, which writes the delegated player and occupancy
+    await erProgram.methods
+      .movePlayer(1, 0, start.moveNonce)
+      .accounts({ player: erPlayerPda, board: erBoardPda })
+      .rpc();
+
+    const player = await erProgram.account.player.fetch(erPlayerPda);
+    expect(player.x).to.equal(start.x + 1);
+    expect(player.y).to.equal(start.y);
   });
 });